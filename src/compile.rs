@@ -0,0 +1,181 @@
+use std::{ collections::HashMap, fs, path::Path };
+
+use crate::error::Result;
+use sqlx::SqliteConnection;
+
+use crate::{ document_similarity_scores, hierarchy::Hierarchy, ordering::{ self, SnippetOrder }, sqlite_interface, COSINE_WEIGHT };
+
+pub const RELATED_THRESHOLD: f32 = 0.2;
+pub const RELATED_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LinkStyle {
+  // `[name](<name.md>)`, relative to the folder of compiled pages
+  Markdown,
+  // `[[name]]`, for Obsidian and other wiki style tools
+  Wikilink,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+  // one page per document, sections are pages like any other
+  Flat,
+  // one page per top-level document with its sections as nested headings
+  Headings,
+  // one page per document, sections in a folder named after their parent
+  Folders,
+}
+
+// Writes every document as a markdown page into dir with its snippets in
+// the given order, stored summaries are placed at the top of their page
+// when with_summary is set
+pub async fn compile_pages(db: &mut SqliteConnection, dir: &Path, style: LinkStyle, layout: Layout, order: SnippetOrder, with_summary: bool) -> Result<usize> {
+  let corpus = ordering::ordered_corpus(sqlite_interface::load_ordered_snippets(db).await?, order);
+  let summaries = if with_summary {
+    sqlite_interface::load_summaries(db).await?
+  } else {
+    HashMap::new()
+  };
+  let corpus_tfidf_data = sqlite_interface::load_tfidf_data(db).await?;
+  let corpus_rake_data = sqlite_interface::load_rake_data(db).await?;
+  let hierarchy = Hierarchy::new(sqlite_interface::load_hierarchy(db).await?);
+
+  let text = |name: &String| corpus.get(name).map(|v| v.as_str()).unwrap_or_default();
+  let summary = |name: &String| summaries.get(name).map(|v| v.as_str());
+
+  let pages: Vec<(String, String)> = match layout {
+    Layout::Flat => {
+      let related = document_similarity_scores(corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT);
+
+      corpus.keys()
+        .map(|name| {
+          let related = related.get(name).map(|v| v.as_slice()).unwrap_or_default();
+          let related = related_pages(related, |document| link(document, style));
+
+          (format!("{}.md", file_name(name)), compile_page(name, text(name), summary(name), &related))
+        })
+        .collect()
+    },
+    Layout::Headings => {
+      // top-level documents are compared on everything below them
+      let top_level = hierarchy.top_level();
+      let related = document_similarity_scores(
+        hierarchy.fold(&corpus_tfidf_data, top_level),
+        hierarchy.fold(&corpus_rake_data, top_level),
+        COSINE_WEIGHT
+      );
+
+      top_level.iter()
+        .map(|name| {
+          let mut body = text(name).to_string();
+          for section in hierarchy.subtree(name).iter().skip(1) {
+            let level = "#".repeat((hierarchy.depth(section) + 1).min(6));
+            let section_summary = summary(section)
+              .map(|summary| format!("{}# Summary\n\n{}\n\n", level, summary))
+              .unwrap_or_default();
+
+            body.push_str(&format!("\n\n{} {}\n\n{}{}", level, section, section_summary, text(section)));
+          }
+
+          let related = related.get(name).map(|v| v.as_slice()).unwrap_or_default();
+          let related = related_pages(related, |document| link(document, style));
+
+          (format!("{}.md", file_name(name)), compile_page(name, body.trim(), summary(name), &related))
+        })
+        .collect()
+    },
+    Layout::Folders => {
+      let related = document_similarity_scores(corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT);
+      let documents: Vec<String> = hierarchy.top_level()
+        .iter()
+        .flat_map(|name| hierarchy.subtree(name))
+        .collect();
+
+      documents.iter()
+        .map(|name| {
+          let depth = hierarchy.depth(name);
+          let folder_link = |document: &str| match style {
+            LinkStyle::Markdown => format!("[{}](<{}{}>)", document, "../".repeat(depth), page_path(document, &hierarchy)),
+            LinkStyle::Wikilink => link(document, style),
+          };
+
+          let mut body = text(name).to_string();
+          let sections: Vec<String> = hierarchy.sections(name)
+            .iter()
+            .map(|section| format!("- {}", folder_link(section)))
+            .collect();
+          if !sections.is_empty() {
+            body.push_str(&format!("\n\n## Sections\n\n{}", sections.join("\n")));
+          }
+
+          let related = related.get(name).map(|v| v.as_slice()).unwrap_or_default();
+          let related = related_pages(related, folder_link);
+
+          (page_path(name, &hierarchy), compile_page(name, body.trim(), summary(name), &related))
+        })
+        .collect()
+    },
+  };
+
+  fs::create_dir_all(dir)?;
+
+  for (path, page) in &pages {
+    let path = dir.join(path);
+    if let Some(folder) = path.parent() {
+      fs::create_dir_all(folder)?;
+    }
+    fs::write(path, page)?;
+  }
+
+  Ok(pages.len())
+}
+
+pub fn compile_page(name: &str, text: &str, summary: Option<&str>, related_pages: &str) -> String {
+  let summary = summary
+    .map(|summary| format!("## Summary\n\n{}\n\n", summary))
+    .unwrap_or_default();
+
+  format!("# {}\n\n{}{}\n{}", name, summary, text, related_pages)
+}
+
+// A "Related pages" section for the most similar documents, empty when
+// none of them reach the threshold
+pub fn related_pages(related: &[(String, f32)], link: impl Fn(&str) -> String) -> String {
+  let links: Vec<String> = related.iter()
+    .filter(|(_, score)| *score >= RELATED_THRESHOLD)
+    .take(RELATED_LIMIT)
+    .map(|(document, _)| format!("- {}", link(document)))
+    .collect();
+
+  if links.is_empty() {
+    String::new()
+  } else {
+    format!("\n## Related pages\n\n{}\n", links.join("\n"))
+  }
+}
+
+// Where a document's page goes in the folders layout, under a folder for
+// each of its parents
+pub fn page_path(document: &str, hierarchy: &Hierarchy) -> String {
+  let path: Vec<String> = hierarchy.path(document)
+    .iter()
+    .map(|document| file_name(document))
+    .collect();
+
+  format!("{}.md", path.join("/"))
+}
+
+pub fn link(document: &str, style: LinkStyle) -> String {
+  match style {
+    LinkStyle::Markdown => format!("[{}](<{}.md>)", document, file_name(document)),
+    LinkStyle::Wikilink => format!("[[{}]]", file_name(document)),
+  }
+}
+
+// A document name that is safe to use as a filename
+pub fn file_name(document: &str) -> String {
+  document.trim()
+    .chars()
+    .map(|c| if "/\\:*?\"<>|#^[]".contains(c) { '-' } else { c })
+    .collect()
+}
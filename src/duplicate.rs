@@ -0,0 +1,161 @@
+use std::collections::HashSet;
+
+use crate::{ preprocess, CorpusSnippets };
+
+// Share of their shingles two snippets need in common to be near duplicates
+pub const MIN_SIMILARITY: f32 = 0.85;
+// number of hash functions in a MinHash signature, the estimated similarity
+// is off by about 1 / sqrt(SIGNATURE_LENGTH)
+pub const SIGNATURE_LENGTH: usize = 256;
+
+// What to do with a snippet that nearly duplicates a stored one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DuplicatePolicy {
+  // store nothing and report the existing snippet
+  Skip,
+  // replace the existing snippet with the new text
+  Merge,
+  // store the snippet anyway, exact copies are still skipped
+  Keep,
+}
+
+// The stored snippet closest to the text as (document, snippet), when their
+// shingles are estimated to overlap by at least MIN_SIMILARITY
+pub fn find_near_duplicate(snippet: &str, corpus_snippets: &CorpusSnippets) -> Option<(String, String)> {
+  let stop_words = preprocess::stop_words(None);
+  let signature = minhash(&shingles(snippet, &stop_words));
+  let mut closest: Option<(f32, &String, &String)> = None;
+
+  for (document, snippets) in corpus_snippets {
+    for existing in snippets {
+      let similarity = if existing == snippet {
+        1.
+      } else {
+        estimated_similarity(&signature, &minhash(&shingles(existing, &stop_words)))
+      };
+
+      if similarity >= MIN_SIMILARITY && closest.is_none_or(|(closest_similarity, _, _)| similarity > closest_similarity) {
+        closest = Some((similarity, document, existing));
+      }
+    }
+  }
+
+  closest.map(|(_, document, existing)| (document.clone(), existing.clone()))
+}
+
+// The stemmed words of a text without its stop words, and every pair of
+// neighbouring ones, so whitespace, case, punctuation and filler words don't
+// matter while word order still does
+pub fn shingles(text: &str, stop_words: &[String]) -> HashSet<String> {
+  let words = preprocess::tfidf_preprocess(text, stop_words.to_vec());
+
+  let mut shingles: HashSet<String> = words.iter().cloned().collect();
+  shingles.extend(words.windows(2).map(|pair| pair.join(" ")));
+
+  shingles
+}
+
+// The smallest value of each of SIGNATURE_LENGTH hash functions over the
+// shingles, two signatures agree at a position with the probability that
+// their shingle sets are Jaccard similar
+pub fn minhash(shingles: &HashSet<String>) -> Vec<u64> {
+  if shingles.is_empty() {
+    return Vec::new();
+  }
+
+  let hashes: Vec<u64> = shingles.iter().map(|shingle| fnv1a(shingle)).collect();
+
+  (0..SIGNATURE_LENGTH as u64)
+    .map(|seed| {
+      let seed = (seed + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+      hashes.iter().map(|hash| mix(hash ^ seed)).min().unwrap_or(u64::MAX)
+    })
+    .collect()
+}
+
+// Share of positions where two signatures agree, a text with no shingles
+// is only ever an exact duplicate
+pub fn estimated_similarity(signature_1: &[u64], signature_2: &[u64]) -> f32 {
+  if signature_1.is_empty() || signature_2.is_empty() {
+    return 0.;
+  }
+
+  let agreeing = signature_1.iter().zip(signature_2).filter(|(a, b)| a == b).count();
+  agreeing as f32 / SIGNATURE_LENGTH as f32
+}
+
+fn fnv1a(text: &str) -> u64 {
+  text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// splitmix64's finaliser, turning one hash into an independent looking one
+// per seed
+fn mix(mut value: u64) -> u64 {
+  value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+  value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+  value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn corpus(snippet: &str) -> CorpusSnippets {
+    CorpusSnippets::from([("Bread".to_string(), vec![snippet.to_string()])])
+  }
+
+  fn similarity(text_1: &str, text_2: &str) -> f32 {
+    let stop_words = preprocess::stop_words(None);
+    estimated_similarity(&minhash(&shingles(text_1, &stop_words)), &minhash(&shingles(text_2, &stop_words)))
+  }
+
+  const SOURDOUGH: &str = "Sourdough bread needs a lively starter, strong white flour, water and salt. The dough is folded every half hour, shaped into a tight boule and given a long cold proof in the fridge before it is scored and baked in a very hot oven with steam";
+
+  #[test]
+  fn one_changed_word_is_a_near_duplicate() {
+    let changed = SOURDOUGH.replace("lively", "healthy");
+    assert_eq!(find_near_duplicate(&changed, &corpus(SOURDOUGH)), Some(("Bread".to_string(), SOURDOUGH.to_string())));
+
+    let extended = SOURDOUGH.replace("steam", "plenty of steam");
+    assert!(find_near_duplicate(&extended, &corpus(SOURDOUGH)).is_some());
+  }
+
+  #[test]
+  fn whitespace_and_case_changes_are_near_duplicates() {
+    let reformatted = SOURDOUGH.to_uppercase().replace(", ", "\n").replace(' ', "  ");
+    assert_eq!(similarity(&reformatted, SOURDOUGH), 1.);
+    assert_eq!(find_near_duplicate(&reformatted, &corpus(SOURDOUGH)), Some(("Bread".to_string(), SOURDOUGH.to_string())));
+  }
+
+  #[test]
+  fn different_statements_on_a_topic_are_not_near_duplicates() {
+    let rust = "Rust is a systems programming language that is fast and memory safe.";
+
+    for statement in [
+      "Go is a systems programming language that is fast and garbage collected.",
+      "C is a systems programming language that is fast and memory unsafe.",
+    ] {
+      assert!(find_near_duplicate(statement, &corpus(rust)).is_none(), "{}", statement);
+    }
+    assert!(find_near_duplicate("rust is quick", &corpus("rust is fast")).is_none());
+  }
+
+  #[test]
+  fn stop_word_only_snippets_are_only_exact_duplicates() {
+    assert!(find_near_duplicate("the and of", &corpus("of and the")).is_none());
+    assert!(find_near_duplicate("the and of", &corpus("the and of")).is_some());
+  }
+
+  #[test]
+  fn signatures_estimate_the_shingle_overlap() {
+    let stop_words = preprocess::stop_words(None);
+    let rust = "Rust is a systems programming language that is fast and memory safe.";
+
+    for (text_1, text_2) in [(SOURDOUGH.replace("lively", "healthy"), SOURDOUGH), ("C is a systems programming language that is fast and memory unsafe.".to_string(), rust)] {
+      let (shingles_1, shingles_2) = (shingles(&text_1, &stop_words), shingles(text_2, &stop_words));
+      let exact = shingles_1.intersection(&shingles_2).count() as f32 / shingles_1.union(&shingles_2).count() as f32;
+
+      assert!((similarity(&text_1, text_2) - exact).abs() < 0.1);
+    }
+  }
+}
@@ -0,0 +1,31 @@
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, PageCompilerError>;
+
+// Everything the library can fail with, nothing it does panics on user input
+#[derive(Debug, Error)]
+pub enum PageCompilerError {
+  #[error("database error: {0}")]
+  Database(#[from] sqlx::Error),
+  #[error("{0}")]
+  Io(#[from] std::io::Error),
+  #[error("{0} doesn't exist")]
+  MissingDocument(String),
+  #[error("No snippet with id {0}")]
+  MissingSnippet(i32),
+  #[error("No revision with id {0}")]
+  MissingRevision(i32),
+  #[error("A snippet can't be empty")]
+  EmptySnippet,
+  #[error("That text is already stored as another snippet")]
+  DuplicateSnippet,
+  #[error("{0} can't be a section of itself or of one of its sections")]
+  CyclicHierarchy(String),
+  #[error("The database predates this version and has to be migrated by init")]
+  Unmigrated,
+  #[error("invalid configuration: {0}")]
+  InvalidConfig(String),
+  // a score came out as NaN or infinite, so no decision can be made from it
+  #[error("scoring failed: {0}")]
+  Scoring(String),
+}
@@ -0,0 +1,159 @@
+use std::{ collections::HashMap, fs, path::{ Path, PathBuf } };
+
+use sqlx::{ Connection, SqliteConnection };
+
+use crate::{ duplicate::DuplicatePolicy, error::{ PageCompilerError, Result }, front_matter, import, submit_snippet, Submission };
+
+// Where a snippet went when it started a document of its own
+pub const NEW_DOCUMENT: &str = "(new)";
+
+// A snippet of the dataset and the document it belongs to
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledSnippet {
+  pub label: String,
+  pub snippet: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Evaluation {
+  pub snippets: usize,
+  pub correct: usize,
+  // snippets that were the first of their label
+  pub new_expected: usize,
+  // snippets that started a document
+  pub new_predicted: usize,
+  pub new_correct: usize,
+  pub duplicates: usize,
+  // (expected, predicted, count) of every kind of mistake, most frequent first
+  pub confusions: Vec<(String, String, usize)>,
+}
+
+impl Evaluation {
+  pub fn accuracy(&self) -> f32 {
+    ratio(self.correct, self.snippets)
+  }
+
+  // how many of the documents started were meant to be
+  pub fn new_precision(&self) -> f32 {
+    ratio(self.new_correct, self.new_predicted)
+  }
+
+  // how many of the documents meant to be started were
+  pub fn new_recall(&self) -> f32 {
+    ratio(self.new_correct, self.new_expected)
+  }
+}
+
+fn ratio(count: usize, total: usize) -> f32 {
+  if total == 0 {
+    0.
+  } else {
+    count as f32 / total as f32
+  }
+}
+
+// A dataset laid out as a directory per document, each markdown file in it
+// split into snippets like `import --snippets` does. Files are replayed in
+// order of their file name across all documents, so numbering them sets the
+// order snippets arrive in
+pub fn read_dataset(dir: &Path) -> Result<Vec<LabeledSnippet>> {
+  let mut files: Vec<(String, String, PathBuf)> = Vec::new();
+
+  for file in import::markdown_files(dir)? {
+    // files outside a document's directory have nothing to be checked against
+    let Some(label) = file.strip_prefix(dir)
+      .ok()
+      .filter(|relative| relative.components().count() > 1)
+      .and_then(|relative| relative.components().next())
+      .map(|label| label.as_os_str().to_string_lossy().to_string()) else {
+      continue;
+    };
+
+    let name = file.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    files.push((name, label, file));
+  }
+
+  files.sort();
+
+  let mut dataset: Vec<LabeledSnippet> = Vec::new();
+  for (_, label, file) in files {
+    let text = fs::read_to_string(&file)?;
+    let (_, text) = front_matter::split_front_matter(&text);
+
+    for snippet in import::split_snippets(text) {
+      dataset.push(LabeledSnippet { label: label.clone(), snippet });
+    }
+  }
+
+  if dataset.is_empty() {
+    return Err(PageCompilerError::InvalidConfig(format!("{} has no snippets in document directories", dir.display())));
+  }
+
+  Ok(dataset)
+}
+
+// Submits every snippet in order to an empty in-memory database and checks
+// where each one went. A document stands for the label of the snippet that
+// started it, so a snippet is right when it starts a document as the first
+// of its label or goes to a document standing for its label
+pub async fn evaluate(dataset: &[LabeledSnippet]) -> Result<Evaluation> {
+  let mut db = SqliteConnection::connect("sqlite::memory:").await?;
+
+  let mut evaluation = Evaluation::default();
+  let mut labels: HashMap<String, String> = HashMap::new();
+  let mut seen: Vec<&str> = Vec::new();
+  let mut confusions: HashMap<(String, String), usize> = HashMap::new();
+
+  for labeled in dataset {
+    let document = match submit_snippet(&labeled.snippet, &mut db, DuplicatePolicy::Skip).await {
+      Ok(Submission::Duplicate(document, _)) => {
+        evaluation.duplicates += 1;
+        document
+      },
+      Ok(Submission::Titled(document) | Submission::Routed(document, _) | Submission::Created(document) | Submission::Merged(document)) => document,
+      // nothing is stored for a snippet with no text, so it isn't scored
+      Err(PageCompilerError::EmptySnippet) => continue,
+      Err(error) => return Err(error),
+    };
+
+    let expected_new = !seen.contains(&labeled.label.as_str());
+    if expected_new {
+      seen.push(&labeled.label);
+    }
+
+    let predicted_new = !labels.contains_key(&document);
+    let predicted = labels.entry(document).or_insert_with(|| labeled.label.clone()).clone();
+
+    let correct = match (expected_new, predicted_new) {
+      (true, true) => true,
+      (false, false) => predicted == labeled.label,
+      _ => false,
+    };
+
+    evaluation.snippets += 1;
+    if correct {
+      evaluation.correct += 1;
+    }
+    if expected_new {
+      evaluation.new_expected += 1;
+    }
+    if predicted_new {
+      evaluation.new_predicted += 1;
+      if expected_new {
+        evaluation.new_correct += 1;
+      }
+    }
+
+    if !correct {
+      let predicted = if predicted_new { NEW_DOCUMENT.to_string() } else { predicted };
+      *confusions.entry((labeled.label.clone(), predicted)).or_default() += 1;
+    }
+  }
+
+  evaluation.confusions = confusions.into_iter()
+    .map(|((expected, predicted), count)| (expected, predicted, count))
+    .collect();
+  evaluation.confusions.sort_by(|a, b| b.2.cmp(&a.2).then((&a.0, &a.1).cmp(&(&b.0, &b.1))));
+
+  Ok(evaluation)
+}
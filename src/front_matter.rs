@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+use crate::preprocess::normalize_tag;
+
+// Keys of a YAML front matter block, scalars are stored as single item lists
+pub type FrontMatter = HashMap<String, Vec<String>>;
+
+// Splits a leading `---` delimited front matter block from the text,
+// only the flat subset of YAML that notes actually use is understood:
+// `key: value`, `key: [a, b]` and `key:` followed by `- item` lines
+pub fn split_front_matter(text: &str) -> (FrontMatter, &str) {
+  let mut front_matter: FrontMatter = HashMap::new();
+  let text = text.trim_start_matches('\u{feff}');
+
+  let Some(rest) = text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) else {
+    return (front_matter, text);
+  };
+
+  let mut offset = text.len() - rest.len();
+  let mut key: Option<String> = None;
+
+  for line in rest.split_inclusive('\n') {
+    offset += line.len();
+    let line = line.trim_end();
+
+    if line == "---" || line == "..." {
+      return (front_matter, &text[offset..]);
+    }
+
+    if line.trim().is_empty() || line.trim_start().starts_with('#') {
+      continue;
+    }
+
+    if let Some(item) = line.trim_start().strip_prefix("- ") {
+      if let Some(key) = &key {
+        front_matter.entry(key.clone()).or_default().push(unquote(item));
+      }
+    } else if let Some((name, value)) = line.split_once(':') {
+      let name = name.trim().to_string();
+      let value = value.trim();
+
+      let values: Vec<String> = if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        list.split(',')
+          .map(unquote)
+          .filter(|v| !v.is_empty())
+          .collect()
+      } else if value.is_empty() {
+        Vec::new()
+      } else {
+        vec![unquote(value)]
+      };
+
+      front_matter.insert(name.clone(), values);
+      key = Some(name);
+    }
+  }
+
+  // an unterminated block is treated as text
+  (HashMap::new(), text)
+}
+
+fn unquote(value: &str) -> String {
+  value.trim()
+    .trim_matches('"')
+    .trim_matches('\'')
+    .to_string()
+}
+
+// Metadata a snippet can carry in its front matter
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SnippetMetadata {
+  pub title: Option<String>,
+  pub document: Option<String>,
+  pub tags: Vec<String>,
+  pub source: Option<String>,
+  pub date: Option<String>,
+  pub language: Option<String>,
+  // the document a titled snippet's document is a section of
+  pub parent: Option<String>,
+}
+
+impl SnippetMetadata {
+  pub fn from_front_matter(front_matter: &FrontMatter) -> Self {
+    let scalar = |key: &str| front_matter.get(key)
+      .and_then(|values| values.first())
+      .filter(|value| !value.is_empty())
+      .cloned();
+
+    SnippetMetadata {
+      title: scalar("title"),
+      document: scalar("document"),
+      tags: front_matter.get("tags")
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tag| normalize_tag(&tag))
+        .filter(|tag| !tag.is_empty())
+        .collect(),
+      source: scalar("source"),
+      date: scalar("date"),
+      language: scalar("language"),
+      parent: scalar("parent"),
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    *self == SnippetMetadata::default()
+  }
+}
+
+// Splits a snippet into its metadata and the text that gets preprocessed
+pub fn parse_snippet(snippet: &str) -> (SnippetMetadata, &str) {
+  let (front_matter, text) = split_front_matter(snippet);
+
+  (SnippetMetadata::from_front_matter(&front_matter), text.trim_start())
+}
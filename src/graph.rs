@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::error::Result;
+use sqlx::SqliteConnection;
+
+use crate::{ document_similarity_scores, sqlite_interface, COSINE_WEIGHT };
+
+pub const EDGE_THRESHOLD: f32 = 0.2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+  pub source: String,
+  pub target: String,
+  pub weight: f32,
+}
+
+// Documents linked by their similarity, edges are undirected
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Graph {
+  pub nodes: Vec<String>,
+  pub edges: Vec<Edge>,
+}
+
+pub async fn document_graph(db: &mut SqliteConnection, threshold: f32) -> Result<Graph> {
+  let corpus_tfidf_data = sqlite_interface::load_tfidf_data(db).await?;
+  let corpus_rake_data = sqlite_interface::load_rake_data(db).await?;
+
+  let scores = document_similarity_scores(corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT);
+
+  Ok(similarity_graph(scores, threshold))
+}
+
+// Keeps each pair of documents once, when their score reaches the threshold
+pub fn similarity_graph(scores: HashMap<String, Vec<(String, f32)>>, threshold: f32) -> Graph {
+  let mut nodes: Vec<String> = scores.keys().cloned().collect();
+  nodes.sort();
+
+  let mut edges: Vec<Edge> = Vec::new();
+  for (source, targets) in scores {
+    for (target, weight) in targets {
+      if source < target && weight >= threshold {
+        edges.push(Edge { source: source.clone(), target, weight });
+      }
+    }
+  }
+  edges.sort_by(|a, b| (&a.source, &a.target).cmp(&(&b.source, &b.target)));
+
+  Graph { nodes, edges }
+}
+
+impl Graph {
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("graph documents {\n");
+
+    for node in &self.nodes {
+      dot.push_str(&format!("  {};\n", dot_string(node)));
+    }
+    for edge in &self.edges {
+      dot.push_str(&format!(
+        "  {} -- {} [weight={:.4}, label=\"{:.2}\"];\n",
+        dot_string(&edge.source),
+        dot_string(&edge.target),
+        edge.weight,
+        edge.weight
+      ));
+    }
+
+    dot.push_str("}\n");
+    dot
+  }
+
+  pub fn to_graphml(&self) -> String {
+    let mut graphml = String::from(concat!(
+      "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+      "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+      "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"double\"/>\n",
+      "  <graph id=\"documents\" edgedefault=\"undirected\">\n",
+    ));
+
+    for node in &self.nodes {
+      graphml.push_str(&format!("    <node id=\"{}\"/>\n", xml_string(node)));
+    }
+    for edge in &self.edges {
+      graphml.push_str(&format!(
+        "    <edge source=\"{}\" target=\"{}\">\n      <data key=\"weight\">{}</data>\n    </edge>\n",
+        xml_string(&edge.source),
+        xml_string(&edge.target),
+        edge.weight
+      ));
+    }
+
+    graphml.push_str("  </graph>\n</graphml>\n");
+    graphml
+  }
+
+  pub fn to_json(&self) -> String {
+    let nodes: Vec<String> = self.nodes.iter()
+      .map(|node| format!("    {{ \"id\": {} }}", json_string(node)))
+      .collect();
+
+    let edges: Vec<String> = self.edges.iter()
+      .map(|edge| format!(
+        "    {{ \"source\": {}, \"target\": {}, \"weight\": {} }}",
+        json_string(&edge.source),
+        json_string(&edge.target),
+        edge.weight
+      ))
+      .collect();
+
+    format!("{{\n  \"nodes\": [\n{}\n  ],\n  \"edges\": [\n{}\n  ]\n}}\n", nodes.join(",\n"), edges.join(",\n"))
+  }
+}
+
+fn dot_string(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn xml_string(text: &str) -> String {
+  text.replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+    .replace('\'', "&apos;")
+}
+
+pub fn json_string(text: &str) -> String {
+  let mut json = String::from("\"");
+
+  for c in text.chars() {
+    match c {
+      '"' => json.push_str("\\\""),
+      '\\' => json.push_str("\\\\"),
+      '\n' => json.push_str("\\n"),
+      '\r' => json.push_str("\\r"),
+      '\t' => json.push_str("\\t"),
+      c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+      c => json.push(c),
+    }
+  }
+
+  json.push('"');
+  json
+}
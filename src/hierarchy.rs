@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use crate::{ combined_similarity_scores, error::{ PageCompilerError, Result }, sqlite_interface::DocumentNode, CorpusSnippets, COSINE_WEIGHT, TAG_WEIGHT };
+
+// Documents arranged under their parents, sections keep their stored order
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hierarchy {
+  top_level: Vec<String>,
+  parents: HashMap<String, String>,
+  sections: HashMap<String, Vec<String>>,
+}
+
+impl Hierarchy {
+  // Nodes are expected in the order load_hierarchy returns them
+  pub fn new(nodes: Vec<DocumentNode>) -> Self {
+    let mut hierarchy = Hierarchy::default();
+
+    for node in nodes {
+      match node.parent_name {
+        Some(parent) => {
+          hierarchy.parents.insert(node.document_name.clone(), parent.clone());
+          hierarchy.sections.entry(parent).or_default().push(node.document_name);
+        },
+        None => hierarchy.top_level.push(node.document_name),
+      }
+    }
+
+    hierarchy
+  }
+
+  pub fn top_level(&self) -> &[String] {
+    &self.top_level
+  }
+
+  pub fn parent(&self, document: &str) -> Option<&str> {
+    self.parents.get(document).map(|parent| parent.as_str())
+  }
+
+  pub fn sections(&self, document: &str) -> &[String] {
+    self.sections.get(document).map(|sections| sections.as_slice()).unwrap_or_default()
+  }
+
+  // The document and every section below it, depth first
+  pub fn subtree(&self, document: &str) -> Vec<String> {
+    let mut subtree = vec![document.to_string()];
+
+    for section in self.sections(document) {
+      // a cycle can only come from editing the database by hand
+      if !subtree.contains(section) {
+        subtree.extend(self.subtree(section));
+      }
+    }
+
+    subtree
+  }
+
+  // The top-level document above the document down to the document itself
+  pub fn path(&self, document: &str) -> Vec<String> {
+    let mut path = vec![document.to_string()];
+
+    while let Some(parent) = self.parent(&path[0]) {
+      if path.iter().any(|document| document == parent) {
+        break;
+      }
+      path.insert(0, parent.to_string());
+    }
+
+    path
+  }
+
+  pub fn depth(&self, document: &str) -> usize {
+    self.path(document).len() - 1
+  }
+
+  // The data of each document merged with that of all its sections, so a
+  // document stands for everything below it
+  pub fn fold(&self, data: &CorpusSnippets, documents: &[String]) -> CorpusSnippets {
+    let mut folded: CorpusSnippets = HashMap::new();
+
+    for document in documents {
+      let mut values: Vec<String> = Vec::new();
+
+      for section in self.subtree(document) {
+        for value in data.get(&section).into_iter().flatten() {
+          if !values.contains(value) {
+            values.push(value.clone());
+          }
+        }
+      }
+
+      if !values.is_empty() {
+        folded.insert(document.clone(), values);
+      }
+    }
+
+    folded
+  }
+}
+
+// Picks the best top-level document, each standing for its whole subtree,
+// then descends while one of its sections scores better than its own
+// snippets. The chosen document is returned with its score against every
+// document on its own, which is what the routing threshold is tuned for.
+// A score that isn't a number can't be compared to the threshold, so it's
+// an error rather than a route
+pub fn route(input_tfidf_data: &[String], input_rake_data: &[String], input_tags: &[String], corpus_tfidf_data: &CorpusSnippets, corpus_rake_data: &CorpusSnippets, corpus_tag_data: &CorpusSnippets, hierarchy: &Hierarchy) -> Result<Option<(String, f32)>> {
+  let scores = |mut tfidf_data: CorpusSnippets, mut rake_data: CorpusSnippets, tag_data: CorpusSnippets| {
+    // both scores are needed for a document to be compared at all
+    tfidf_data.retain(|document, _| rake_data.contains_key(document));
+    rake_data.retain(|document, _| tfidf_data.contains_key(document));
+
+    combined_similarity_scores(input_tfidf_data.to_vec(), input_rake_data.to_vec(), input_tags.to_vec(), tfidf_data, rake_data, tag_data, COSINE_WEIGHT, TAG_WEIGHT)
+  };
+
+  let level_scores = |documents: &[String], own: Option<&String>| {
+    let mut tfidf_data = hierarchy.fold(corpus_tfidf_data, documents);
+    let mut rake_data = hierarchy.fold(corpus_rake_data, documents);
+    let mut tag_data = hierarchy.fold(corpus_tag_data, documents);
+
+    // the parent competes with its sections on its own snippets only
+    if let Some(own) = own {
+      for (data, corpus_data) in [(&mut tfidf_data, corpus_tfidf_data), (&mut rake_data, corpus_rake_data), (&mut tag_data, corpus_tag_data)] {
+        if let Some(values) = corpus_data.get(own) {
+          data.insert(own.clone(), values.clone());
+        }
+      }
+    }
+
+    scores(tfidf_data, rake_data, tag_data)
+  };
+
+  let Some((mut document, _)) = level_scores(hierarchy.top_level(), None).first().cloned() else {
+    return Ok(None);
+  };
+
+  while !hierarchy.sections(&document).is_empty() {
+    match level_scores(hierarchy.sections(&document), Some(&document)).first() {
+      Some((section, score)) if *section != document && *score > 0. => document = section.clone(),
+      _ => break,
+    }
+  }
+
+  let score = scores(corpus_tfidf_data.clone(), corpus_rake_data.clone(), corpus_tag_data.clone())
+    .into_iter()
+    .find(|(scored, _)| *scored == document)
+    .map(|(_, score)| score)
+    .unwrap_or_default();
+
+  if !score.is_finite() {
+    return Err(PageCompilerError::Scoring(format!("{} scored {}", document, score)));
+  }
+
+  Ok(Some((document, score)))
+}
@@ -0,0 +1,78 @@
+use crate::error::Result;
+use sqlx::{ Connection, SqliteConnection };
+
+use crate::{ revise_snippet, sqlite_interface };
+
+// Puts a snippet back to an earlier revision, its text is re-indexed and it
+// returns to the document it was in at the time. The version it replaces is
+// kept as a revision of its own, so a restore can be undone the same way
+pub async fn restore_revision(db: &mut SqliteConnection, revision_id: i32) -> Result<String> {
+  let revision = sqlite_interface::load_revision(db, revision_id).await?;
+
+  let mut tx = db.begin().await?;
+  let operation = sqlite_interface::begin_operation(&mut tx, "restore", &format!("revision {}", revision_id)).await?;
+
+  let document = revise_snippet(revision.snippet_id, &revision.snippet, &mut tx, false, "restore").await?;
+  if document != revision.document_name {
+    sqlite_interface::move_snippet(&mut tx, revision.snippet_id, &revision.document_name).await?;
+  }
+
+  sqlite_interface::end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(revision.document_name)
+}
+
+// A word level diff in the style of `git diff --word-diff`, removed words
+// are wrapped in [-...-] and added words in {+...+}
+pub fn diff(old: &str, new: &str) -> String {
+  let old: Vec<&str> = old.split_inclusive(char::is_whitespace).collect();
+  let new: Vec<&str> = new.split_inclusive(char::is_whitespace).collect();
+
+  // lengths of the longest common subsequence of every pair of suffixes
+  let mut lengths = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+  for i in (0..old.len()).rev() {
+    for j in (0..new.len()).rev() {
+      lengths[i][j] = if old[i].trim_end() == new[j].trim_end() {
+        lengths[i + 1][j + 1] + 1
+      } else {
+        lengths[i + 1][j].max(lengths[i][j + 1])
+      };
+    }
+  }
+
+  let mut diff = String::new();
+  let (mut removed, mut added) = (String::new(), String::new());
+  let (mut i, mut j) = (0, 0);
+
+  while i < old.len() || j < new.len() {
+    if i < old.len() && j < new.len() && old[i].trim_end() == new[j].trim_end() {
+      push_changes(&mut diff, &mut removed, &mut added);
+      // the last word of either text has no whitespace after it
+      diff.push_str(if new[j].len() >= old[i].len() { new[j] } else { old[i] });
+      i += 1;
+      j += 1;
+    } else if j < new.len() && (i == old.len() || lengths[i][j + 1] >= lengths[i + 1][j]) {
+      added.push_str(new[j]);
+      j += 1;
+    } else {
+      removed.push_str(old[i]);
+      i += 1;
+    }
+  }
+  push_changes(&mut diff, &mut removed, &mut added);
+
+  diff
+}
+
+// Writes out the pending removed and added words, keeping the whitespace
+// that follows them outside the markers
+fn push_changes(diff: &mut String, removed: &mut String, added: &mut String) {
+  for (words, open, close) in [(&mut *removed, "[-", "-]"), (&mut *added, "{+", "+}")] {
+    if !words.is_empty() {
+      let text = words.trim_end();
+      diff.push_str(&format!("{}{}{}{}", open, text, close, &words[text.len()..]));
+      words.clear();
+    }
+  }
+}
@@ -0,0 +1,214 @@
+use std::{ fs, path::{ Path, PathBuf } };
+
+use crate::error::Result;
+use sqlx::{ SqliteConnection, SqlitePool };
+use stop_words::{ get, LANGUAGE };
+
+use crate::{ duplicate::DuplicatePolicy, front_matter, preprocess, sqlite_interface, submit_snippet, Submission };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImportMode {
+  // each file is a document named after its first heading or filename
+  Documents,
+  // each file is split into snippets which are routed like any submission
+  Snippets,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ImportSummary {
+  pub files: usize,
+  pub documents_created: Vec<String>,
+  pub snippets_added: usize,
+  pub snippets_routed: usize,
+  // snippets that started a new document with a generated title
+  pub snippets_created: usize,
+  pub snippets_duplicate: usize,
+}
+
+pub async fn import_directory(db: &SqlitePool, dir: &Path, mode: ImportMode) -> Result<ImportSummary> {
+  let mut summary = ImportSummary::default();
+
+  let mut conn = db.acquire().await?;
+  sqlite_interface::init(&mut conn).await?;
+  let existing_documents = sqlite_interface::load_document_names(&mut conn).await?;
+  drop(conn);
+
+  for file in markdown_files(dir)? {
+    let text = fs::read_to_string(&file)?;
+    let (front_matter, text) = front_matter::split_front_matter(&text);
+
+    // one transaction per file so a failure never leaves a file half imported
+    let mut tx = db.begin().await?;
+    let operation = sqlite_interface::begin_operation(&mut tx, "import", &file.to_string_lossy()).await?;
+
+    match mode {
+      ImportMode::Documents => {
+        let (title, body) = split_title(text);
+        let name = front_matter.get("title")
+          .and_then(|title| title.first().cloned())
+          .or(title)
+          .unwrap_or_else(|| file_stem(&file));
+
+        let (added, duplicate) = add_document_snippets(&mut tx, &name, split_snippets(&body)).await?;
+        summary.snippets_added += added;
+        summary.snippets_duplicate += duplicate;
+      },
+      ImportMode::Snippets => {
+        for snippet in split_snippets(text) {
+          match submit_snippet(&snippet, &mut tx, DuplicatePolicy::Skip).await? {
+            Submission::Titled(_) => summary.snippets_added += 1,
+            Submission::Routed(_, _) => summary.snippets_routed += 1,
+            Submission::Created(_) => summary.snippets_created += 1,
+            Submission::Duplicate(_, _) | Submission::Merged(_) => summary.snippets_duplicate += 1,
+          }
+        }
+      },
+    }
+
+    sqlite_interface::end_operation(&mut tx, operation).await?;
+    tx.commit().await?;
+    summary.files += 1;
+  }
+
+  let mut conn = db.acquire().await?;
+  summary.documents_created = sqlite_interface::load_document_names(&mut conn)
+    .await?
+    .into_iter()
+    .filter(|name| !existing_documents.contains(name))
+    .collect();
+
+  Ok(summary)
+}
+
+// Adds the snippets of a pre-titled text to the named document, returning
+// how many were stored and how many were skipped as already stored
+pub async fn add_document_snippets(db: &mut SqliteConnection, name: &str, snippets: Vec<String>) -> Result<(usize, usize)> {
+  let stop_words = get(LANGUAGE::English);
+  let snippets: Vec<String> = snippets
+    .iter()
+    .map(|snippet| preprocess::normalize_snippet(snippet))
+    .collect();
+
+  let (mut added, mut duplicate) = (0, 0);
+  for snippet in &snippets {
+    let tfidf_data = preprocess::tfidf_preprocess(snippet, stop_words.clone());
+    let rake_data = preprocess::rake_preprocess(snippet, stop_words.clone());
+
+    if sqlite_interface::add_document(db, name, snippet, tfidf_data, rake_data).await?.is_some() {
+      sqlite_interface::add_snippet_tags(db, snippet, &preprocess::extract_tags(snippet)).await?;
+      added += 1;
+    } else {
+      duplicate += 1;
+    }
+  }
+
+  Ok((added, duplicate))
+}
+
+// Markdown files under dir, recursively, in a stable order, hidden
+// directories such as `.obsidian` or `.git` are skipped
+pub fn markdown_files(dir: &Path) -> Result<Vec<PathBuf>> {
+  let mut files = Vec::new();
+
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+
+    if is_hidden(&path) {
+      continue;
+    } else if path.is_dir() {
+      files.extend(markdown_files(&path)?);
+    } else if path.extension().is_some_and(|extension| extension == "md") {
+      files.push(path);
+    }
+  }
+
+  files.sort();
+
+  Ok(files)
+}
+
+// Splits a markdown file into snippets on headings and horizontal rules,
+// headings are kept as the plain first line of the snippet that follows them
+pub fn split_snippets(text: &str) -> Vec<String> {
+  split(text, false)
+}
+
+// Splits a markdown file like split_snippets, keeping each heading's markup
+// for notes that are compiled back into the same document
+pub fn split_sections(text: &str) -> Vec<String> {
+  split(text, true)
+}
+
+fn split(text: &str, keep_headings: bool) -> Vec<String> {
+  let mut snippets: Vec<String> = Vec::new();
+  let mut snippet: Vec<&str> = Vec::new();
+
+  for line in text.lines() {
+    if let Some(heading) = heading(line) {
+      push_snippet(&mut snippets, &snippet);
+      snippet.clear();
+      snippet.push(if keep_headings { line.trim() } else { heading });
+    } else if is_horizontal_rule(line) {
+      push_snippet(&mut snippets, &snippet);
+      snippet.clear();
+    } else {
+      snippet.push(line);
+    }
+  }
+  push_snippet(&mut snippets, &snippet);
+
+  snippets
+}
+
+// The first heading of a file and the text without it
+pub fn split_title(text: &str) -> (Option<String>, String) {
+  let mut lines = text.lines();
+  let mut body: Vec<&str> = Vec::new();
+
+  for line in lines.by_ref() {
+    if let Some(title) = heading(line) {
+      body.extend(lines);
+      return (Some(title.to_string()), body.join("\n"));
+    }
+    body.push(line);
+  }
+
+  (None, text.to_string())
+}
+
+fn push_snippet(snippets: &mut Vec<String>, lines: &[&str]) {
+  let snippet = lines.join("\n").trim().to_string();
+
+  if !snippet.is_empty() {
+    snippets.push(snippet);
+  }
+}
+
+fn heading(line: &str) -> Option<&str> {
+  let trimmed = line.trim_start();
+  let text = trimmed.trim_start_matches('#');
+  let level = trimmed.len() - text.len();
+
+  if (1..=6).contains(&level) && text.starts_with(' ') {
+    Some(text.trim())
+  } else {
+    None
+  }
+}
+
+fn is_horizontal_rule(line: &str) -> bool {
+  let marks: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+
+  marks.len() >= 3
+    && ['-', '*', '_'].iter().any(|mark| marks.chars().all(|c| c == *mark))
+}
+
+fn is_hidden(path: &Path) -> bool {
+  path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+pub fn file_stem(path: &Path) -> String {
+  path.file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
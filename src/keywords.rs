@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use crate::error::Result;
+use sqlx::SqliteConnection;
+
+use crate::{ graph::{ json_string, xml_string }, preprocess, rake, sqlite_interface, tf_idf };
+
+pub const KEYWORD_LIMIT: usize = 10;
+
+const SVG_WIDTH: f32 = 600.;
+const MIN_FONT_SIZE: f32 = 12.;
+const MAX_FONT_SIZE: f32 = 48.;
+
+// What a document is about, its top tf-idf terms and RAKE keyphrases
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keywords {
+  pub document: String,
+  pub terms: Vec<(String, f32)>,
+  pub phrases: Vec<(String, f32)>,
+}
+
+pub async fn document_keywords(db: &mut SqliteConnection, limit: usize) -> Result<Vec<Keywords>> {
+  // the stored terms are unique per snippet, so they're counted again from
+  // the text for their frequencies
+  let stop_words = preprocess::stop_words(None);
+  let corpus_tfidf_data = preprocess::corpus_tfidf_preprocess(sqlite_interface::load_corpus(db).await?, stop_words);
+  let corpus_rake_data = sqlite_interface::load_rake_data(db).await?;
+  let surface_forms = sqlite_interface::load_surface_forms(db).await?;
+
+  let tfidf_scores = tf_idf::smoothed_tf_idf_vectors(&corpus_tfidf_data);
+  let rake_scores = rake::corpus_rake(corpus_rake_data.clone());
+
+  let mut documents: Vec<&String> = corpus_tfidf_data.keys().chain(corpus_rake_data.keys()).collect();
+  documents.sort();
+  documents.dedup();
+
+  let keywords = documents.into_iter()
+    .map(|document| {
+      let document_surface_forms = surface_forms.get(document).cloned().unwrap_or_default();
+
+      let terms = top_scores(
+        tfidf_scores.get(document).cloned().unwrap_or_default(),
+        &document_surface_forms,
+        limit
+      );
+
+      // rake scores words as well as phrases, only the phrases are keyphrases
+      let phrases = corpus_rake_data.get(document).cloned().unwrap_or_default();
+      let phrase_scores: HashMap<String, f32> = rake_scores.get(document)
+        .cloned()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(phrase, _)| phrases.contains(phrase))
+        .collect();
+
+      Keywords {
+        document: document.to_string(),
+        terms,
+        phrases: top_scores(phrase_scores, &document_surface_forms, limit),
+      }
+    })
+    .collect();
+
+  Ok(keywords)
+}
+
+// The highest scores in surface form, stems that share a surface form keep
+// their best score
+fn top_scores(scores: HashMap<String, f32>, surface_forms: &HashMap<String, String>, limit: usize) -> Vec<(String, f32)> {
+  let mut surface_scores: HashMap<String, f32> = HashMap::new();
+  for (stem, score) in scores {
+    let score_entry = surface_scores.entry(preprocess::unstem(&stem, surface_forms)).or_default();
+    *score_entry = score_entry.max(score);
+  }
+
+  let mut top: Vec<(String, f32)> = surface_scores.into_iter().collect();
+  top.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+  top.truncate(limit);
+
+  top
+}
+
+impl Keywords {
+  pub fn to_markdown(&self) -> String {
+    let mut markdown = format!("## {}\n\n### Terms\n\n", self.document);
+
+    for (term, score) in &self.terms {
+      markdown.push_str(&format!("- {} ({:.3})\n", term, score));
+    }
+
+    markdown.push_str("\n### Keyphrases\n\n");
+    for (phrase, score) in &self.phrases {
+      markdown.push_str(&format!("- {} ({:.3})\n", phrase, score));
+    }
+
+    markdown
+  }
+
+  pub fn to_json(&self) -> String {
+    let scores = |scores: &[(String, f32)]| scores.iter()
+      .map(|(text, score)| format!("{{ \"text\": {}, \"score\": {} }}", json_string(text), score))
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    format!(
+      "{{ \"document\": {}, \"terms\": [{}], \"phrases\": [{}] }}",
+      json_string(&self.document),
+      scores(&self.terms),
+      scores(&self.phrases)
+    )
+  }
+
+  // Terms and phrases laid out in rows, sized by their score relative to
+  // the best of their kind
+  pub fn to_svg(&self) -> String {
+    let normalise = |scores: &[(String, f32)]| {
+      let max = scores.iter().map(|(_, score)| *score).fold(0., f32::max);
+      scores.iter()
+        .map(|(text, score)| (text.clone(), if max > 0. { score / max } else { 0. }))
+        .collect::<Vec<(String, f32)>>()
+    };
+
+    let mut words = normalise(&self.terms);
+    words.extend(normalise(&self.phrases));
+    words.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    let mut seen: Vec<String> = Vec::new();
+    words.retain(|(word, _)| {
+      let new = !seen.contains(word);
+      seen.push(word.clone());
+      new
+    });
+
+    let mut text = String::new();
+    let (mut x, mut y, mut row_height) = (0., 0., 0_f32);
+
+    for (word, weight) in words {
+      let size = MIN_FONT_SIZE + (MAX_FONT_SIZE - MIN_FONT_SIZE) * weight;
+      // a rough width, svg has no text measurement without a renderer
+      let width = word.chars().count() as f32 * size * 0.6;
+
+      if x > 0. && x + width > SVG_WIDTH {
+        x = 0.;
+        y += row_height;
+        row_height = 0.;
+      }
+      row_height = row_height.max(size * 1.2);
+
+      text.push_str(&format!(
+        "  <text x=\"{:.0}\" y=\"{:.0}\" font-size=\"{:.0}\">{}</text>\n",
+        x,
+        y + size,
+        size,
+        xml_string(&word)
+      ));
+      x += width + size * 0.5;
+    }
+
+    format!(
+      "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-family=\"sans-serif\">\n{}</svg>\n",
+      SVG_WIDTH,
+      y + row_height,
+      text
+    )
+  }
+}
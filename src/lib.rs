@@ -0,0 +1,449 @@
+use std::{ cmp::Reverse, collections::{ HashMap, HashSet } };
+
+use sqlx::{Connection, SqliteConnection};
+use error::{ PageCompilerError, Result };
+
+use duplicate::DuplicatePolicy;
+
+pub mod error;
+pub mod sqlite_interface;
+pub mod similarity;
+pub mod preprocess;
+pub mod tf_idf;
+pub mod rake;
+pub mod import;
+pub mod front_matter;
+pub mod obsidian;
+pub mod graph;
+pub mod compile;
+pub mod summary;
+pub mod title;
+pub mod keywords;
+pub mod duplicate;
+pub mod hierarchy;
+pub mod ordering;
+pub mod history;
+pub mod evaluate;
+
+pub type CorpusSnippets = HashMap<String, Vec<String>>;
+pub type Corpus = HashMap<String, String>;
+
+pub const COSINE_WEIGHT: f32 = 0.4;
+pub const THESHOLD: f32 = 0.6;
+// how much better than its own document another has to score for an edited
+// snippet to be moved there
+pub const REROUTE_MARGIN: f32 = 0.1;
+// share of the score given to tag overlap when the input carries tags
+pub const TAG_WEIGHT: f32 = 0.2;
+
+// What happened to a snippet once it was submitted
+#[derive(Debug, Clone, PartialEq)]
+pub enum Submission {
+  Titled(String),
+  Routed(String, f32),
+  Created(String),
+  // the document and text of the stored snippet it duplicates
+  Duplicate(String, String),
+  // the document whose near duplicate snippet was replaced
+  Merged(String),
+}
+
+// A document related to a query, with the snippets that match it best
+#[derive(Debug, Clone, PartialEq)]
+pub struct Related {
+  pub document: String,
+  pub score: f32,
+  // terms the query shares with the document, in surface form
+  pub terms: Vec<String>,
+  pub excerpts: Vec<String>,
+}
+
+pub const EXCERPT_LENGTH: usize = 160;
+// how much of a snippet is kept to describe an operation in the journal
+const OPERATION_DETAIL_LENGTH: usize = 60;
+const RELATED_EXCERPTS: usize = 2;
+
+pub async fn submit_snippet(snippet: &str, db: &mut SqliteConnection, policy: DuplicatePolicy) -> Result<Submission> {
+  let (metadata, snippet) = front_matter::parse_snippet(snippet);
+  let snippet = preprocess::normalize_snippet(snippet);
+  let snippet = snippet.as_str();
+
+  if snippet.is_empty() {
+    return Err(PageCompilerError::EmptySnippet);
+  }
+
+  // the whole submission is one transaction, a failure part way through
+  // leaves nothing behind
+  let mut tx = db.begin().await?;
+
+  sqlite_interface::init(&mut tx).await?;
+  let operation = sqlite_interface::begin_operation(&mut tx, "submit", &excerpt(snippet, OPERATION_DETAIL_LENGTH)).await?;
+
+  let corpus_snippets = sqlite_interface::load_corpus_snippets(&mut tx).await?;
+  // empty parent documents have nothing to route against
+  let first_entry = corpus_snippets.is_empty();
+
+  // kept near duplicates are stored, but an exact copy can't be stored twice
+  let duplicate = match policy {
+    DuplicatePolicy::Keep => corpus_snippets.iter()
+      .find(|(_, snippets)| snippets.iter().any(|existing| existing == snippet))
+      .map(|(document, _)| (document.clone(), snippet.to_string())),
+    _ => duplicate::find_near_duplicate(snippet, &corpus_snippets),
+  };
+
+  // an explicit target document wins over a title, which wins over a #title line
+  let first_line = snippet.lines().next().unwrap_or_default();
+  let title: Option<&str> = metadata.document.as_deref()
+    .or(metadata.title.as_deref())
+    .or(first_line.strip_prefix('#'));
+
+  let stop_words = preprocess::stop_words(metadata.language.as_deref());
+
+  let input_tfidf_data = preprocess::tfidf_preprocess(snippet, stop_words.clone());
+  let input_rake_data = preprocess::rake_preprocess(snippet, stop_words.clone());
+
+  let mut input_tags = metadata.tags.clone();
+  for tag in preprocess::extract_tags(snippet) {
+    if !input_tags.contains(&tag) {
+      input_tags.push(tag);
+    }
+  }
+
+  let submission = if let Some((document, existing)) = duplicate {
+    if policy == DuplicatePolicy::Merge && existing != snippet {
+      let document = sqlite_interface::replace_snippet(&mut tx, &existing, snippet, input_tfidf_data, input_rake_data).await?;
+      Submission::Merged(document)
+    } else {
+      sqlite_interface::end_operation(&mut tx, operation).await?;
+      tx.commit().await?;
+
+      return Ok(Submission::Duplicate(document, existing));
+    }
+  } else if first_entry {
+    if let Some(title) = title {
+      sqlite_interface::add_document(&mut tx, title, snippet, input_tfidf_data, input_rake_data).await?;
+      Submission::Titled(title.to_string())
+    } else {
+      let title = title::generate_title(&input_rake_data, &preprocess::surface_forms(snippet), &[]);
+
+      sqlite_interface::add_document(&mut tx, &title, snippet, input_tfidf_data, input_rake_data).await?;
+      Submission::Created(title)
+    }
+  } else {
+    let corpus_tfidf_data = sqlite_interface::load_tfidf_data(&mut tx).await?;
+    let corpus_rake_data = sqlite_interface::load_rake_data(&mut tx).await?;
+    let corpus_tag_data = sqlite_interface::load_tag_data(&mut tx).await?;
+    let hierarchy = hierarchy::Hierarchy::new(sqlite_interface::load_hierarchy(&mut tx).await?);
+
+    if let Some(title) = title {
+      sqlite_interface::add_document(&mut tx, title, snippet, input_tfidf_data, input_rake_data).await?;
+      Submission::Titled(title.to_string())
+    } else {
+      let route = hierarchy::route(&input_tfidf_data, &input_rake_data, &input_tags, &corpus_tfidf_data, &corpus_rake_data, &corpus_tag_data, &hierarchy)?;
+
+      if let Some((document, score)) = route.filter(|(_, score)| *score >= THESHOLD) {
+        sqlite_interface::add_document(&mut tx, &document, snippet, input_tfidf_data, input_rake_data).await?;
+        Submission::Routed(document, score)
+      } else {
+        let existing_documents = sqlite_interface::load_document_names(&mut tx).await?;
+        let title = title::generate_title(&input_rake_data, &preprocess::surface_forms(snippet), &existing_documents);
+
+        sqlite_interface::add_document(&mut tx, &title, snippet, input_tfidf_data, input_rake_data).await?;
+        Submission::Created(title)
+      }
+    }
+  };
+
+  if let (Submission::Titled(document), Some(parent)) = (&submission, &metadata.parent) {
+    sqlite_interface::set_parent(&mut tx, document, Some(parent)).await?;
+  }
+
+  if !metadata.is_empty() {
+    sqlite_interface::add_snippet_metadata(&mut tx, snippet, &metadata).await?;
+  }
+  sqlite_interface::add_snippet_tags(&mut tx, snippet, &input_tags).await?;
+
+  sqlite_interface::end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(submission)
+}
+
+// Replaces a stored snippet's text and re-indexes it in one transaction.
+// With reroute set the snippet is scored like a new submission and moved
+// when another document meets the threshold and beats its own document by
+// REROUTE_MARGIN, since its own document still counts its new text it only
+// moves for a clearly better fit.
+// Returns the document the snippet ends up in
+pub async fn edit_snippet(snippet_id: i32, snippet: &str, db: &mut SqliteConnection, reroute: bool) -> Result<String> {
+  revise_snippet(snippet_id, snippet, db, reroute, "edit").await
+}
+
+// An edit recorded in the snippet's history under the given reason
+pub(crate) async fn revise_snippet(snippet_id: i32, snippet: &str, db: &mut SqliteConnection, reroute: bool, reason: &str) -> Result<String> {
+  let (metadata, snippet) = front_matter::parse_snippet(snippet);
+  let snippet = preprocess::normalize_snippet(snippet);
+  let snippet = snippet.as_str();
+
+  if snippet.is_empty() {
+    return Err(PageCompilerError::EmptySnippet);
+  }
+
+  sqlite_interface::init(db).await?;
+  let current = sqlite_interface::load_snippet(db, snippet_id).await?;
+
+  if sqlite_interface::find_snippet(db, snippet).await?.is_some_and(|existing| existing != snippet_id) {
+    return Err(PageCompilerError::DuplicateSnippet);
+  }
+
+  let stop_words = preprocess::stop_words(metadata.language.as_deref());
+
+  let input_tfidf_data = preprocess::tfidf_preprocess(snippet, stop_words.clone());
+  let input_rake_data = preprocess::rake_preprocess(snippet, stop_words.clone());
+
+  // new front matter replaces the stored metadata, without any the tags the
+  // old front matter gave are kept along with its title and source
+  let mut input_tags = if metadata.is_empty() {
+    let inline_tags = preprocess::extract_tags(&current.snippet);
+    sqlite_interface::load_snippet_tags(db, snippet_id)
+      .await?
+      .into_iter()
+      .filter(|tag| !inline_tags.contains(tag))
+      .collect()
+  } else {
+    metadata.tags.clone()
+  };
+  for tag in preprocess::extract_tags(snippet) {
+    if !input_tags.contains(&tag) {
+      input_tags.push(tag);
+    }
+  }
+
+  let mut tx = db.begin().await?;
+  let operation = sqlite_interface::begin_operation(&mut tx, reason, &format!("snippet {}", snippet_id)).await?;
+
+  // the same text without front matter has nothing to re-index, so only a
+  // reroute can make it an operation worth undoing
+  let mut document = current.document_name.clone();
+  if current.snippet != snippet || !metadata.is_empty() {
+    if current.snippet != snippet {
+      sqlite_interface::add_revision(&mut tx, snippet_id, reason).await?;
+    }
+    document = sqlite_interface::update_snippet(&mut tx, snippet_id, snippet, input_tfidf_data.clone(), input_rake_data.clone()).await?;
+
+    sqlite_interface::remove_snippet_tags(&mut tx, snippet_id).await?;
+    sqlite_interface::add_snippet_tags(&mut tx, snippet, &input_tags).await?;
+    if !metadata.is_empty() {
+      sqlite_interface::add_snippet_metadata(&mut tx, snippet, &metadata).await?;
+    }
+  }
+
+  if reroute {
+    let corpus_tfidf_data = sqlite_interface::load_tfidf_data(&mut tx).await?;
+    let corpus_rake_data = sqlite_interface::load_rake_data(&mut tx).await?;
+    let corpus_tag_data = sqlite_interface::load_tag_data(&mut tx).await?;
+    let hierarchy = hierarchy::Hierarchy::new(sqlite_interface::load_hierarchy(&mut tx).await?);
+
+    let route = hierarchy::route(&input_tfidf_data, &input_rake_data, &input_tags, &corpus_tfidf_data, &corpus_rake_data, &corpus_tag_data, &hierarchy)?;
+
+    let current_score = combined_similarity_scores(input_tfidf_data.clone(), input_rake_data.clone(), input_tags.clone(), corpus_tfidf_data.clone(), corpus_rake_data.clone(), corpus_tag_data.clone(), COSINE_WEIGHT, TAG_WEIGHT)
+      .into_iter()
+      .find(|(scored, _)| *scored == document)
+      .map(|(_, score)| score)
+      .unwrap_or_default();
+
+    if let Some((best, _)) = route.filter(|(best, score)| *best != document && *score >= THESHOLD && *score >= current_score + REROUTE_MARGIN) {
+      sqlite_interface::move_snippet(&mut tx, snippet_id, &best).await?;
+      document = best;
+    }
+  }
+
+  sqlite_interface::end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(document)
+}
+
+// Scores the query against every document like a submission would,
+// without writing anything to the database
+pub async fn related(query: &str, n: usize, db: &mut SqliteConnection) -> Result<Vec<Related>> {
+  let (metadata, query) = front_matter::parse_snippet(query);
+
+  let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type='table' AND name IN ('Document', 'Tag', 'Surface_Form')")
+    .fetch_all(&mut *db)
+    .await?;
+
+  // a database nothing was ever stored in has nothing related, one from an
+  // older version has to go through init before it can be searched
+  if !tables.iter().any(|table| table == "Document") {
+    return Ok(Vec::new());
+  } else if tables.len() < 3 {
+    return Err(PageCompilerError::Unmigrated);
+  }
+
+  let stop_words = preprocess::stop_words(metadata.language.as_deref());
+
+  let input_tfidf_data = preprocess::tfidf_preprocess(query, stop_words.clone());
+  let input_rake_data = preprocess::rake_preprocess(query, stop_words.clone());
+
+  let mut input_tags = metadata.tags.clone();
+  for tag in preprocess::extract_tags(query) {
+    if !input_tags.contains(&tag) {
+      input_tags.push(tag);
+    }
+  }
+
+  let corpus_tfidf_data = sqlite_interface::load_tfidf_data(db).await?;
+  let corpus_rake_data = sqlite_interface::load_rake_data(db).await?;
+  let corpus_tag_data = sqlite_interface::load_tag_data(db).await?;
+  let corpus_snippets = sqlite_interface::load_corpus_snippets(db).await?;
+  let surface_forms = sqlite_interface::load_surface_forms(db).await?;
+
+  let scores = combined_similarity_scores(input_tfidf_data.clone(), input_rake_data, input_tags, corpus_tfidf_data.clone(), corpus_rake_data, corpus_tag_data, COSINE_WEIGHT, TAG_WEIGHT);
+
+  let related = scores.into_iter()
+    .take(n)
+    .map(|(document, score)| {
+      // snippets sharing the most terms with the query come first
+      let mut snippets: Vec<(usize, &String)> = corpus_snippets.get(&document)
+        .map(|snippets| snippets.iter()
+          .map(|snippet| {
+            let terms = preprocess::tfidf_preprocess(snippet, stop_words.clone());
+            let shared = input_tfidf_data.iter().filter(|term| terms.contains(term)).count();
+            (shared, snippet)
+          })
+          .collect())
+        .unwrap_or_default();
+      snippets.sort_by_key(|(shared, _)| Reverse(*shared));
+
+      let document_terms = corpus_tfidf_data.get(&document).cloned().unwrap_or_default();
+      let document_surface_forms = surface_forms.get(&document).cloned().unwrap_or_default();
+
+      let mut terms: Vec<String> = Vec::new();
+      for term in input_tfidf_data.iter().filter(|term| document_terms.contains(term)) {
+        let term = preprocess::unstem(term, &document_surface_forms);
+        if !terms.contains(&term) {
+          terms.push(term);
+        }
+      }
+
+      Related {
+        document,
+        score,
+        terms,
+        excerpts: snippets.into_iter()
+          .take(RELATED_EXCERPTS)
+          .map(|(_, snippet)| excerpt(snippet, EXCERPT_LENGTH))
+          .collect(),
+      }
+    })
+    .collect();
+
+  Ok(related)
+}
+
+// The start of a text on a single line, cut at a character boundary
+pub fn excerpt(text: &str, length: usize) -> String {
+  let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+  if text.chars().count() <= length {
+    text
+  } else {
+    format!("{}...", text.chars().take(length).collect::<String>().trim_end())
+  }
+}
+
+// Tag overlap only contributes when the input has tags, so untagged
+// snippets are scored on their text alone
+#[allow(clippy::too_many_arguments)]
+pub fn combined_similarity_scores(input_tfidf_data: Vec<String>, input_rake_data: Vec<String>, input_tags: Vec<String>, corpus_tfidf_data: CorpusSnippets, corpus_rake_data: CorpusSnippets, corpus_tags: CorpusSnippets, cosine_weight: f32, tag_weight: f32) -> Vec<(String, f32)> {
+  let corpus_tfidf_scores = tf_idf::corpus_tf_idf_hash(corpus_tfidf_data.clone());
+  let corpus_rake_scores = rake::corpus_rake(corpus_rake_data.clone());
+
+  let tf_idf_input_score = tf_idf::tf_idf_hash(input_tfidf_data, corpus_tfidf_data);
+  let rake_input_score = rake::rake(input_rake_data.clone());
+
+  let documents_1: HashSet<&str> = corpus_tfidf_scores.keys().map(|k| k.as_str()).collect();
+  let documents_2: HashSet<&str> = corpus_rake_scores.keys().map(|k| k.as_str()).collect();
+  let all_documents: HashSet<&str> = documents_1.union(&documents_2).map(|v| v.to_owned()).collect();
+
+  let mut combined_scores: HashMap<String, f32> = HashMap::new();
+
+  for document in all_documents {
+    let cosine_similarity_score =
+      similarity::cosine_similarity_tuple(tf_idf_input_score.clone(), corpus_tfidf_scores.get(document).cloned().unwrap_or_default())
+      * cosine_weight;
+
+    let weighted_jaccard_similarity_score =
+      similarity::weighted_jaccard_similarity(
+        input_rake_data.clone(),
+        corpus_rake_data.get(document).cloned().unwrap_or_default(),
+        rake_input_score.clone(),
+        corpus_rake_scores.get(document).cloned().unwrap_or_default()
+      )
+      * (1. - cosine_weight);
+
+    let mut score = cosine_similarity_score + weighted_jaccard_similarity_score;
+
+    if !input_tags.is_empty() {
+      let tag_similarity_score = similarity::jaccard_similarity(
+        input_tags.clone(),
+        corpus_tags.get(document).cloned().unwrap_or_default()
+      );
+
+      score = score * (1. - tag_weight) + tag_similarity_score * tag_weight;
+    }
+
+    combined_scores.insert(document.to_string(), score);
+  }
+
+  let mut sorted_scores: Vec<(String, f32)> = combined_scores.into_iter().collect();
+  sorted_scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+  sorted_scores
+}
+
+// Scores every stored document against every other one, highest first
+pub fn document_similarity_scores(corpus_tfidf_data: CorpusSnippets, corpus_rake_data: CorpusSnippets, cosine_weight: f32) -> HashMap<String, Vec<(String, f32)>> {
+  let corpus_tfidf_scores = tf_idf::corpus_tf_idf_hash(corpus_tfidf_data);
+  let corpus_rake_scores = rake::corpus_rake(corpus_rake_data.clone());
+
+  let mut all_documents: Vec<&String> = corpus_tfidf_scores.keys().chain(corpus_rake_scores.keys()).collect();
+  all_documents.sort();
+  all_documents.dedup();
+
+  let mut document_scores: HashMap<String, Vec<(String, f32)>> = HashMap::new();
+
+  for document in all_documents.clone() {
+    let mut scores: Vec<(String, f32)> = Vec::new();
+
+    for other in all_documents.clone() {
+      if document == other {
+        continue;
+      }
+
+      let cosine_similarity_score =
+        similarity::cosine_similarity_tuple(
+          corpus_tfidf_scores.get(document).cloned().unwrap_or_default(),
+          corpus_tfidf_scores.get(other).cloned().unwrap_or_default()
+        )
+        * cosine_weight;
+
+      let weighted_jaccard_similarity_score =
+        similarity::weighted_jaccard_similarity(
+          corpus_rake_data.get(document).cloned().unwrap_or_default(),
+          corpus_rake_data.get(other).cloned().unwrap_or_default(),
+          corpus_rake_scores.get(document).cloned().unwrap_or_default(),
+          corpus_rake_scores.get(other).cloned().unwrap_or_default()
+        )
+        * (1. - cosine_weight);
+
+      scores.push((other.to_string(), cosine_similarity_score + weighted_jaccard_similarity_score));
+    }
+
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    document_scores.insert(document.to_string(), scores);
+  }
+
+  document_scores
+}
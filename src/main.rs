@@ -1,180 +1,451 @@
-use std::collections::{ HashMap, HashSet };
+use std::{ env, fs, path::Path, process, str::FromStr };
 
 use sqlx::{migrate::MigrateDatabase, Sqlite, SqlitePool};
-use stop_words::{ get, LANGUAGE };
-use anyhow::{Ok, Result};
 use dirs::home_dir;
 
-mod sqlite_interface;
-mod similarity;
-mod preprocess;
-mod tf_idf;
-mod rake;
-
-pub type CorpusSnippets = HashMap<String, Vec<String>>;
-pub type Corpus = HashMap<String, String>;
+use page_compiler::{ compile, duplicate::DuplicatePolicy, edit_snippet, error::{ PageCompilerError, Result }, evaluate, excerpt, graph, hierarchy::Hierarchy, history, import, ordering::SnippetOrder, keywords, obsidian, preprocess, related, sqlite_interface, submit_snippet, summary, Submission, EXCERPT_LENGTH };
 
 const PATH: &str = "dev/rust/page_compiler/src/data.db";
-const COSINE_WEIGHT: f32 = 0.4;
-const THESHOLD: f32 = 0.6;
+
+const USAGE: &str = "usage:
+  page_compiler submit <snippet> [--duplicates skip|merge|keep]
+  page_compiler edit <snippet id> <snippet> [--reroute]
+  page_compiler history <snippet id>
+  page_compiler diff <revision> [<revision>]
+  page_compiler restore <revision>
+  page_compiler journal [--limit <n>]
+  page_compiler undo [<n>]
+  page_compiler import <dir> [--snippets]
+  page_compiler evaluate <dataset>
+  page_compiler compile <dir> [--wikilinks] [--summary] [--layout flat|headings|folders] [--order position|chronological|coherence]
+  page_compiler summarize [<document>] [--sentences <n>]
+  page_compiler obsidian import <vault> [--attachments <dir>]
+  page_compiler obsidian export <vault>
+  page_compiler nest <document> <parent>
+  page_compiler unnest <document>
+  page_compiler tree
+  page_compiler snippets <document>
+  page_compiler move <snippet id> up|down|<position>
+  page_compiler tags
+  page_compiler tag <tag>
+  page_compiler related <query> [--limit <n>]
+  page_compiler keywords [<document>] [--format markdown|json|svg] [--limit <n>] [--output <dir>]
+  page_compiler graph [--format dot|graphml|json] [--threshold <score>] [--output <file>]
+  page_compiler search <query> [--document <name>] [--tag <tag>] [--from <date>] [--to <date>] [--limit <n>]";
+
+const SEARCH_LIMIT: i64 = 10;
+const RELATED_LIMIT: usize = 5;
+const JOURNAL_LIMIT: u32 = 20;
 
 #[async_std::main]
-async fn main() -> Result<()>{
-  let path = home_dir()
-    .expect("Unable to find home directory")
-    .join(PATH)
-    .into_os_string()
-    .into_string()
-    .unwrap();
+async fn main() {
+  if let Err(error) = run().await {
+    eprintln!("Error: {}", error);
+    process::exit(1);
+  }
+}
+
+async fn run() -> Result<()> {
+  let Some(home) = home_dir() else {
+    return Err(PageCompilerError::InvalidConfig("unable to find the home directory".to_string()));
+  };
+  let path = home.join(PATH);
+  let Some(path) = path.to_str().map(|path| path.to_string()) else {
+    return Err(PageCompilerError::InvalidConfig(format!("{} isn't valid unicode", path.display())));
+  };
 
   if !Sqlite::database_exists(&path).await.unwrap_or(false) {
     println!("Creating database: {}", &path);
     Sqlite::create_database(&path).await?;
   }
 
-  let db = SqlitePool::connect(&path).await.unwrap();
-
-  let snippet = "#title\nlua is a great scripting language that can be used with other programming languages like rust, it is similar in simplicty to python, however lua is more so used in embedded applications and in some cases game development";
+  let db = SqlitePool::connect(&path).await?;
+
+  let args: Vec<String> = env::args().skip(1).collect();
+
+  match args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>().as_slice() {
+    ["submit", snippet, flags @ ..] => {
+      let policy = match flag(flags, "--duplicates") {
+        Some("merge") => DuplicatePolicy::Merge,
+        Some("keep") => DuplicatePolicy::Keep,
+        _ => DuplicatePolicy::Skip,
+      };
+
+      let mut conn = db.acquire().await?;
+      match submit_snippet(snippet, &mut conn, policy).await? {
+        Submission::Duplicate(document, existing) => {
+          println!("Skipped, already stored in {}:", document);
+          println!("  {}", excerpt(&existing, EXCERPT_LENGTH));
+        },
+        Submission::Merged(document) => println!("Replaced the near duplicate in {}", document),
+        Submission::Titled(document) => println!("Added to {}", document),
+        Submission::Routed(document, score) => println!("{} is the chosen document with a score of {}", document, score),
+        Submission::Created(document) => println!("Creating new document {}", document),
+      }
+    },
+    ["edit", snippet_id, snippet, flags @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let snippet_id = number(snippet_id)?;
+      let previous = sqlite_interface::load_snippet(&mut conn, snippet_id).await?.document_name;
+      let document = edit_snippet(snippet_id, snippet, &mut conn, flags.contains(&"--reroute")).await?;
+      if document != previous {
+        println!("Moving the snippet from {} to {}", previous, document);
+      }
+      println!("Snippet {} in {} was updated", snippet_id, document);
+    },
+    ["history", snippet_id] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let snippet_id = number(snippet_id)?;
+      for revision in sqlite_interface::load_revisions(&mut conn, snippet_id).await? {
+        println!("{:>4}  {}  {:<7}  {}", revision.revision_id, revision.created_at, revision.reason, revision.document_name);
+        println!("      {}", excerpt(&revision.snippet, EXCERPT_LENGTH / 2));
+      }
 
-  submit_snippet(snippet, &db).await?;
+      let current = sqlite_interface::load_snippet(&mut conn, snippet_id).await?;
+      println!("now   {}", current.document_name);
+      println!("      {}", excerpt(&current.snippet, EXCERPT_LENGTH / 2));
+    },
+    ["diff", from, rest @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let from = sqlite_interface::load_revision(&mut conn, number(from)?).await?;
+      // without a second revision the diff is against the current text
+      let to = match rest {
+        [to] => sqlite_interface::load_revision(&mut conn, number(to)?).await?.snippet,
+        _ => sqlite_interface::load_snippet(&mut conn, from.snippet_id).await?.snippet,
+      };
+
+      println!("{}", history::diff(&from.snippet, &to));
+    },
+    ["restore", revision_id] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let document = history::restore_revision(&mut conn, number(revision_id)?).await?;
+      println!("Restored revision {} in {}", revision_id, document);
+    },
+    ["journal", flags @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let limit = flag(flags, "--limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(JOURNAL_LIMIT);
+
+      for operation in sqlite_interface::load_operations(&mut conn, limit).await? {
+        println!("{:>4}  {}  {:<9}  {}", operation.operation_id, operation.created_at, operation.kind, operation.detail);
+      }
+    },
+    ["undo", rest @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let n = match rest {
+        [n] => number(n)?,
+        _ => 1,
+      };
+
+      let operations = sqlite_interface::undo_operations(&mut conn, n).await?;
+      if operations.is_empty() {
+        println!("Nothing to undo");
+      }
+      for operation in operations {
+        println!("Undid {} {}", operation.kind, operation.detail);
+      }
+    },
+    ["import", dir, flags @ ..] => {
+      let mode = if flags.contains(&"--snippets") {
+        import::ImportMode::Snippets
+      } else {
+        import::ImportMode::Documents
+      };
 
-  Ok(())
-}
+      let summary = import::import_directory(&db, Path::new(dir), mode).await?;
 
-async fn submit_snippet(snippet: &str, db: &SqlitePool) -> Result<()> {
-  let first_entry = sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='Document'")
-    .fetch_all(db)
-    .await?
-    .is_empty();
-
-  let first_line = snippet.lines().collect::<Vec<&str>>()[0];
-  let title: Option<&str> = first_line.strip_prefix('#');
-
-  let stop_words = get(LANGUAGE::English);
-
-  let input_tfidf_data = preprocess::tfidf_preprocess(snippet, stop_words.clone());
-  let input_rake_data = preprocess::rake_preprocess(snippet, stop_words.clone());
-
-  if first_entry {
-    sqlite_interface::init(db).await?;
-    
-    if let Some(title) = title {
-      sqlite_interface::add_document(db, title, snippet, input_tfidf_data, input_rake_data).await?;
-    } else {
-      sqlite_interface::add_document(db, "first document", snippet, input_tfidf_data, input_rake_data).await?;
-    }
-  } else {
-    let corpus_tfidf_data = sqlite_interface::load_tfidf_data(db).await?;
-    let corpus_rake_data = sqlite_interface::load_rake_data(db).await?;
-
-    if let Some(title) = title {
-      sqlite_interface::add_document(db, title, snippet, input_tfidf_data, input_rake_data).await?;
-    } else {
-      let scores = combined_similarity_scores(input_tfidf_data.clone(), input_rake_data.clone(), corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT);
-
-      if scores[0].1 >= THESHOLD {
-        println!("{} is the chosen document with a score of {}", scores[0].0, scores[0].1);
-
-        sqlite_interface::add_snippet(db, snippet, &scores[0].0).await?;
-        sqlite_interface::update_tfidf_data(db, input_tfidf_data, &scores[0].0).await?;
-        sqlite_interface::update_rake_data(db, input_rake_data, &scores[0].0).await?;
+      println!("Imported {} files", summary.files);
+      println!("Created {} documents", summary.documents_created.len());
+      for document in &summary.documents_created {
+        println!("  {}", document);
+      }
+      println!("Added {} titled snippets", summary.snippets_added);
+      println!("Routed {} snippets", summary.snippets_routed);
+      println!("Started {} new documents from snippets", summary.snippets_created);
+      println!("Skipped {} duplicate snippets", summary.snippets_duplicate);
+    },
+    ["evaluate", dataset] => {
+      let dataset = evaluate::read_dataset(Path::new(dataset))?;
+      let evaluation = evaluate::evaluate(&dataset).await?;
+
+      println!();
+      println!("Snippets      {}", evaluation.snippets);
+      println!("Accuracy      {:.2} ({}/{})", evaluation.accuracy(), evaluation.correct, evaluation.snippets);
+      println!("New documents precision {:.2} ({}/{}), recall {:.2} ({}/{})",
+        evaluation.new_precision(), evaluation.new_correct, evaluation.new_predicted,
+        evaluation.new_recall(), evaluation.new_correct, evaluation.new_expected
+      );
+      println!("Duplicates    {}", evaluation.duplicates);
+
+      if !evaluation.confusions.is_empty() {
+        println!("Confusions (expected -> predicted)");
+        for (expected, predicted, count) in &evaluation.confusions {
+          println!("  {:>4}  {} -> {}", count, expected, predicted);
+        }
+      }
+    },
+    ["compile", dir, flags @ ..] => {
+      let style = if flags.contains(&"--wikilinks") {
+        compile::LinkStyle::Wikilink
       } else {
-        println!("{} doesn't meet the threshold with a score of {}", scores[0].0, scores[0].1);
-        println!("Creating new document");
+        compile::LinkStyle::Markdown
+      };
+
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let layout = match flag(flags, "--layout") {
+        Some("headings") => compile::Layout::Headings,
+        Some("folders") => compile::Layout::Folders,
+        _ => compile::Layout::Flat,
+      };
+
+      let order = match flag(flags, "--order") {
+        Some("chronological") => SnippetOrder::Chronological,
+        Some("coherence") => SnippetOrder::Coherence,
+        _ => SnippetOrder::Position,
+      };
+
+      let pages = compile::compile_pages(&mut conn, Path::new(dir), style, layout, order, flags.contains(&"--summary")).await?;
+      println!("Compiled {} pages to {}", pages, dir);
+    },
+    ["summarize", rest @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let (documents, flags) = match rest {
+        [document, flags @ ..] if !document.starts_with("--") => (vec![document.to_string()], flags),
+        flags => (sqlite_interface::load_document_names(&mut conn).await?, flags),
+      };
+      let sentences = flag(flags, "--sentences")
+        .and_then(|sentences| sentences.parse().ok())
+        .unwrap_or(summary::SUMMARY_SENTENCES);
+
+      for document in documents {
+        match summary::summarize_document(&mut conn, &document, sentences).await? {
+          Some(summary) => println!("{}\n  {}", document, summary),
+          None => println!("{} doesn't exist", document),
+        }
       }
-    }
-  }
+    },
+    ["obsidian", "import", vault, flags @ ..] => {
+      let attachments = flag(flags, "--attachments").unwrap_or(obsidian::ATTACHMENTS);
 
-  Ok(())
-}
+      let summary = obsidian::import_vault(&db, Path::new(vault), attachments).await?;
 
-fn combined_similarity_scores(input_tfidf_data: Vec<String>, input_rake_data: Vec<String>, corpus_tfidf_data: CorpusSnippets, corpus_rake_data: CorpusSnippets, cosine_weight: f32) -> Vec<(String, f32)> {
-  let corpus_tfidf_scores = tf_idf::corpus_tf_idf_hash(corpus_tfidf_data.clone());
-  let corpus_rake_scores = rake::corpus_rake(corpus_rake_data.clone());
-
-  let tf_idf_input_score = tf_idf::tf_idf_hash(input_tfidf_data, corpus_tfidf_data);
-  let rake_input_score = rake::rake(input_rake_data.clone());
-
-  let documents_1: HashSet<&str> = corpus_tfidf_scores.keys().map(|k| k.as_str()).collect();
-  let documents_2: HashSet<&str> = corpus_rake_scores.keys().map(|k| k.as_str()).collect();
-  let all_documents: HashSet<&str> = documents_1.union(&documents_2).map(|v| v.to_owned()).collect();
-
-  let mut combined_scores: HashMap<String, f32> = HashMap::new();
-
-  for document in all_documents {
-    let cosine_similarity_score = 
-      similarity::cosine_similarity_tuple(tf_idf_input_score.clone(), corpus_tfidf_scores[document].clone())
-      * cosine_weight;
-    
-    let weighted_jaccard_similarity_score = 
-      similarity::weighted_jaccard_similarity(input_rake_data.clone(), corpus_rake_data[document].clone(), rake_input_score.clone(), corpus_rake_scores[document].clone())
-      * (1. - cosine_weight);
-    
-    combined_scores.insert(
-      document.to_string(),
-      cosine_similarity_score + weighted_jaccard_similarity_score
-    );
-  }
+      println!("Imported {} notes", summary.notes);
+      println!("Created {} documents", summary.documents_created.len());
+      for document in &summary.documents_created {
+        println!("  {}", document);
+      }
+      println!("Added {} snippets", summary.snippets_added);
+      println!("Skipped {} duplicate snippets", summary.snippets_duplicate);
+    },
+    ["obsidian", "export", vault] => {
+      let notes = obsidian::export_vault(&db, Path::new(vault)).await?;
+      println!("Exported {} notes to {}", notes, vault);
+    },
+    ["nest", document, parent] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      sqlite_interface::set_parent(&mut conn, document, Some(parent)).await?;
+      println!("{} is now a section of {}", document, parent);
+    },
+    ["unnest", document] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      sqlite_interface::set_parent(&mut conn, document, None).await?;
+      println!("{} is now a top-level document", document);
+    },
+    ["tree"] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let hierarchy = Hierarchy::new(sqlite_interface::load_hierarchy(&mut conn).await?);
+      for document in hierarchy.top_level() {
+        for section in hierarchy.subtree(document) {
+          println!("{}{}", "  ".repeat(hierarchy.depth(&section)), section);
+        }
+      }
+    },
+    ["snippets", document] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
 
-  let mut sorted_scores: Vec<(String, f32)> = combined_scores.into_iter().collect();
-  sorted_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+      for snippet in sqlite_interface::load_document_snippets(&mut conn, document).await? {
+        println!("{:>4}  {}", snippet.snippet_id, excerpt(&snippet.snippet, EXCERPT_LENGTH / 2));
+      }
+    },
+    ["move", snippet_id, to] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let snippet_id = number(snippet_id)?;
+      let position = match *to {
+        "up" => sqlite_interface::move_up(&mut conn, snippet_id).await?,
+        "down" => sqlite_interface::move_down(&mut conn, snippet_id).await?,
+        position => sqlite_interface::set_position(&mut conn, snippet_id, number(position)?).await?,
+      };
+      println!("Snippet {} is now at position {}", snippet_id, position);
+    },
+    ["tags"] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      for tag_count in sqlite_interface::load_tag_counts(&mut conn).await? {
+        println!("#{} ({} snippets, {} documents)", tag_count.tag, tag_count.snippets, tag_count.documents);
+      }
+    },
+    ["tag", tag] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let tagged = sqlite_interface::load_tagged(&mut conn, &preprocess::normalize_tag(tag)).await?;
+      let mut documents: Vec<&String> = tagged.keys().collect();
+      documents.sort();
+
+      for document in documents {
+        println!("{}", document);
+        for snippet in &tagged[document] {
+          println!("  {}", snippet.lines().next().unwrap_or_default());
+        }
+      }
+    },
+    ["related", query, flags @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+      let limit = flag(flags, "--limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(RELATED_LIMIT);
+
+      for document in related(query, limit, &mut conn).await? {
+        println!("{} ({:.2})", document.document, document.score);
+        if !document.terms.is_empty() {
+          println!("  shared terms: {}", document.terms.join(", "));
+        }
+        for excerpt in &document.excerpts {
+          println!("  {}", excerpt);
+        }
+      }
+    },
+    ["keywords", rest @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let (document, flags) = match rest {
+        [document, flags @ ..] if !document.starts_with("--") => (Some(*document), flags),
+        flags => (None, flags),
+      };
+      let limit = flag(flags, "--limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(keywords::KEYWORD_LIMIT);
+
+      let all_keywords: Vec<keywords::Keywords> = keywords::document_keywords(&mut conn, limit)
+        .await?
+        .into_iter()
+        .filter(|keywords| document.is_none_or(|document| keywords.document == document))
+        .collect();
+
+      match flag(flags, "--format").unwrap_or("markdown") {
+        "json" => {
+          let json: Vec<String> = all_keywords.iter().map(|keywords| keywords.to_json()).collect();
+          println!("[\n  {}\n]", json.join(",\n  "));
+        },
+        "svg" => {
+          // one svg document can only hold one cloud
+          if all_keywords.len() > 1 && flag(flags, "--output").is_none() {
+            return Err(PageCompilerError::InvalidConfig("svg keywords of several documents need --output <dir> or a single document".to_string()));
+          }
+
+          for keywords in &all_keywords {
+            match flag(flags, "--output") {
+              Some(dir) => {
+                fs::create_dir_all(dir)?;
+                fs::write(Path::new(dir).join(format!("{}.svg", compile::file_name(&keywords.document))), keywords.to_svg())?;
+              },
+              None => print!("{}", keywords.to_svg()),
+            }
+          }
+        },
+        _ => {
+          for keywords in &all_keywords {
+            println!("{}", keywords.to_markdown());
+          }
+        },
+      }
+    },
+    ["graph", flags @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let threshold = flag(flags, "--threshold")
+        .and_then(|threshold| threshold.parse().ok())
+        .unwrap_or(graph::EDGE_THRESHOLD);
+      let graph = graph::document_graph(&mut conn, threshold).await?;
+
+      let exported = match flag(flags, "--format").unwrap_or("dot") {
+        "graphml" => graph.to_graphml(),
+        "json" => graph.to_json(),
+        _ => graph.to_dot(),
+      };
+
+      match flag(flags, "--output") {
+        Some(output) => {
+          fs::write(output, exported)?;
+          println!("Wrote {} documents and {} edges to {}", graph.nodes.len(), graph.edges.len(), output);
+        },
+        None => print!("{}", exported),
+      }
+    },
+    ["search", query, flags @ ..] => {
+      let mut conn = db.acquire().await?;
+      sqlite_interface::init(&mut conn).await?;
+
+      let filter = sqlite_interface::SearchFilter {
+        document: flag(flags, "--document").map(|document| document.to_string()),
+        tag: flag(flags, "--tag").map(preprocess::normalize_tag),
+        from: flag(flags, "--from").map(|date| date.to_string()),
+        to: flag(flags, "--to").map(|date| date.to_string()),
+      };
+      let limit = flag(flags, "--limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(SEARCH_LIMIT);
+
+      for result in sqlite_interface::search(&mut conn, query, &filter, limit).await? {
+        println!("{} ({:.2})", result.document_name, -result.rank);
+        println!("  {}", result.excerpt.replace('\n', " "));
+      }
+    },
+    _ => println!("{}", USAGE),
+  }
 
-  sorted_scores
+  Ok(())
 }
 
-fn get_test_corpus() -> HashMap<String, String> {
-  let mut corpus = HashMap::new();
-
-  corpus.insert(
-    "doc1".to_string(),
-    "machine learning is fun".to_string(),
-  );
-
-  corpus.insert(
-    "doc2".to_string(),
-    "deep learning is powerful".to_string(),
-  );
-
-  corpus.insert(
-    "doc3".to_string(),
-    "artificial intelligence is the future".to_string(),
-  );
-
-  corpus.insert(
-    "doc4".to_string(),
-    "machine intelligence is rising".to_string(),
-  );
-
-  corpus.insert(
-    "doc5".to_string(),
-    "rust programming is fast and safe".to_string(),
-  );
-
-  corpus.insert(
-    "doc6".to_string(),
-    "python is great for data science".to_string(),
-  );
-
-  corpus.insert(
-    "doc7".to_string(),
-    "data science requires statistics".to_string(),
-  );
-
-  corpus.insert(
-    "doc8".to_string(),
-    "statistics is the backbone of ML".to_string(),
-  );
-
-  corpus.insert(
-    "doc9".to_string(),
-    "deep neural networks are revolutionary".to_string(),
-  );
-
-  corpus.insert(
-    "doc10".to_string(),
-    "rust and python are both awesome languages".to_string(),
-  );
-
-  corpus
+// The value following a `--name` flag
+fn flag<'a>(flags: &[&'a str], name: &str) -> Option<&'a str> {
+  flags.iter()
+    .position(|flag| *flag == name)
+    .and_then(|i| flags.get(i + 1))
+    .copied()
 }
 
+// A number given on the command line
+fn number<T: FromStr>(value: &str) -> Result<T> {
+  value.parse().map_err(|_| PageCompilerError::InvalidConfig(format!("{} isn't a valid number", value)))
+}
@@ -0,0 +1,173 @@
+use std::{ fs, path::{ Path, PathBuf } };
+
+use crate::error::Result;
+use sqlx::SqlitePool;
+
+use crate::{ compile::{ self, LinkStyle }, document_similarity_scores, front_matter, import, preprocess, sqlite_interface, COSINE_WEIGHT };
+
+pub const ATTACHMENTS: &str = "attachments";
+
+#[derive(Debug, Clone)]
+pub struct VaultNote {
+  pub path: PathBuf,
+  pub title: String,
+  pub tags: Vec<String>,
+  pub links: Vec<String>,
+  pub body: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VaultSummary {
+  pub notes: usize,
+  pub documents_created: Vec<String>,
+  pub snippets_added: usize,
+  pub snippets_duplicate: usize,
+}
+
+pub fn read_note(path: &Path) -> Result<VaultNote> {
+  let text = fs::read_to_string(path)?;
+  let (front_matter, body) = front_matter::split_front_matter(&text);
+
+  let title = front_matter.get("title")
+    .and_then(|title| title.first().cloned())
+    .unwrap_or_else(|| import::file_stem(path));
+
+  let tags = front_matter.get("tags")
+    .cloned()
+    .unwrap_or_default()
+    .into_iter()
+    .map(|tag| preprocess::normalize_tag(&tag))
+    .filter(|tag| !tag.is_empty())
+    .collect();
+
+  let (body, links) = resolve_wikilinks(body);
+
+  Ok(VaultNote {
+    path: path.to_path_buf(),
+    title,
+    tags,
+    links,
+    body,
+  })
+}
+
+// Every note in the vault becomes a document, skipping the attachments folder
+pub async fn import_vault(db: &SqlitePool, vault: &Path, attachments: &str) -> Result<VaultSummary> {
+  let mut summary = VaultSummary::default();
+
+  let mut conn = db.acquire().await?;
+  sqlite_interface::init(&mut conn).await?;
+  let existing_documents = sqlite_interface::load_document_names(&mut conn).await?;
+  drop(conn);
+
+  let attachments = vault.join(attachments);
+  let notes: Vec<PathBuf> = import::markdown_files(vault)?
+    .into_iter()
+    .filter(|path| !path.starts_with(&attachments))
+    .collect();
+
+  let mut tx = db.begin().await?;
+  let operation = sqlite_interface::begin_operation(&mut tx, "import", &vault.to_string_lossy()).await?;
+
+  for path in notes {
+    let note = read_note(&path)?;
+
+    let (added, duplicate) = import::add_document_snippets(&mut tx, &note.title, import::split_sections(&note.body)).await?;
+    summary.snippets_added += added;
+    summary.snippets_duplicate += duplicate;
+    sqlite_interface::add_document_tags(&mut tx, &note.title, &note.tags).await?;
+    sqlite_interface::add_document_links(&mut tx, &note.title, &note.links).await?;
+    summary.notes += 1;
+  }
+
+  sqlite_interface::end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  let mut conn = db.acquire().await?;
+  summary.documents_created = sqlite_interface::load_document_names(&mut conn)
+    .await?
+    .into_iter()
+    .filter(|name| !existing_documents.contains(name))
+    .collect();
+
+  Ok(summary)
+}
+
+// Writes every compiled document as a note with the links it was imported
+// with, followed by the most similar documents it doesn't already link to
+pub async fn export_vault(db: &SqlitePool, vault: &Path) -> Result<usize> {
+  let mut conn = db.acquire().await?;
+  sqlite_interface::init(&mut conn).await?;
+  let corpus = sqlite_interface::load_corpus(&mut conn).await?;
+  let corpus_tfidf_data = sqlite_interface::load_tfidf_data(&mut conn).await?;
+  let corpus_rake_data = sqlite_interface::load_rake_data(&mut conn).await?;
+  let corpus_tag_data = sqlite_interface::load_tag_data(&mut conn).await?;
+  let document_links = sqlite_interface::load_document_links(&mut conn).await?;
+
+  let related = document_similarity_scores(corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT);
+
+  fs::create_dir_all(vault)?;
+
+  for (name, text) in &corpus {
+    let mut tags = corpus_tag_data.get(name).cloned().unwrap_or_default();
+    tags.sort();
+
+    let mut note = format!("---\ntitle: \"{}\"\n", name.replace('"', "\\\""));
+    if !tags.is_empty() {
+      note.push_str(&format!("tags: [{}]\n", tags.join(", ")));
+    }
+    note.push_str(&format!("---\n\n{}\n", text));
+
+    let links = document_links.get(name).cloned().unwrap_or_default();
+    if !links.is_empty() {
+      let links: Vec<String> = links.iter()
+        .map(|target| format!("- {}", compile::link(target, LinkStyle::Wikilink)))
+        .collect();
+      note.push_str(&format!("\n## Links\n\n{}\n", links.join("\n")));
+    }
+
+    let related: Vec<(String, f32)> = related.get(name)
+      .map(|v| v.as_slice())
+      .unwrap_or_default()
+      .iter()
+      .filter(|(document, _)| !links.contains(document))
+      .cloned()
+      .collect();
+    note.push_str(&compile::related_pages(&related, |document| compile::link(document, LinkStyle::Wikilink)));
+
+    fs::write(vault.join(format!("{}.md", compile::file_name(name))), note)?;
+  }
+
+  Ok(corpus.len())
+}
+
+// Replaces `[[target|alias]]` with its display text and drops `![[embeds]]`,
+// returning the link targets alongside the text
+pub fn resolve_wikilinks(text: &str) -> (String, Vec<String>) {
+  let mut resolved = String::with_capacity(text.len());
+  let mut links: Vec<String> = Vec::new();
+  let mut rest = text;
+
+  while let Some(start) = rest.find("[[") {
+    let Some(end) = rest[start..].find("]]") else {
+      break;
+    };
+
+    let embed = rest[..start].ends_with('!');
+    resolved.push_str(&rest[..if embed { start - 1 } else { start }]);
+
+    let link = &rest[start + 2..start + end];
+    let (target, alias) = link.split_once('|').unwrap_or((link, link));
+    let target = target.split('#').next().unwrap_or_default().trim();
+
+    if !embed {
+      resolved.push_str(alias.trim());
+      links.push(target.to_string());
+    }
+
+    rest = &rest[start + end + 2..];
+  }
+  resolved.push_str(rest);
+
+  (resolved, links)
+}
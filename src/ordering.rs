@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use crate::{ preprocess, similarity, sqlite_interface::OrderedSnippet, tf_idf, Corpus, CorpusSnippets };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnippetOrder {
+  // oldest first, by metadata date or when the snippet was stored
+  Chronological,
+  // the stored position, which starts as the order snippets were added
+  Position,
+  // each snippet followed by the remaining one most similar to it
+  Coherence,
+}
+
+// Every document's snippets joined in the given order
+pub fn ordered_corpus(snippets: Vec<OrderedSnippet>, order: SnippetOrder) -> Corpus {
+  let mut documents: HashMap<String, Vec<OrderedSnippet>> = HashMap::new();
+  for snippet in snippets {
+    documents.entry(snippet.document_name.clone()).or_default().push(snippet);
+  }
+
+  documents.into_iter()
+    .map(|(document, snippets)| (document, order_snippets(snippets, order).join("\n\n")))
+    .collect()
+}
+
+pub fn order_snippets(mut snippets: Vec<OrderedSnippet>, order: SnippetOrder) -> Vec<String> {
+  // ties and missing values fall back to the order snippets were stored in
+  match order {
+    SnippetOrder::Chronological => snippets.sort_by(|a, b| (&a.date, a.snippet_id).cmp(&(&b.date, b.snippet_id))),
+    SnippetOrder::Position => snippets.sort_by_key(|snippet| (snippet.position, snippet.snippet_id)),
+    SnippetOrder::Coherence => {
+      snippets.sort_by(|a, b| (&a.date, a.snippet_id).cmp(&(&b.date, b.snippet_id)));
+
+      let texts: Vec<String> = snippets.into_iter().map(|snippet| snippet.snippet).collect();
+      return coherent_order(&texts).into_iter().map(|i| texts[i].clone()).collect();
+    },
+  }
+
+  snippets.into_iter().map(|snippet| snippet.snippet).collect()
+}
+
+// Greedily chains snippets starting from the first, always moving on to the
+// unused snippet with the highest cosine similarity to the last one placed,
+// returning their indices
+pub fn coherent_order(snippets: &[String]) -> Vec<usize> {
+  if snippets.is_empty() {
+    return Vec::new();
+  }
+
+  let stop_words = preprocess::stop_words(None);
+  let corpus: CorpusSnippets = snippets.iter()
+    .enumerate()
+    .map(|(i, snippet)| (i.to_string(), preprocess::tfidf_preprocess(snippet, stop_words.clone())))
+    .collect();
+  let vectors = tf_idf::tf_idf_vectors(&corpus);
+
+  let mut order = vec![0];
+  let mut remaining: Vec<usize> = (1..snippets.len()).collect();
+
+  while !remaining.is_empty() {
+    let last = &vectors[&order[order.len() - 1].to_string()];
+
+    // the earliest snippet wins a tie, so unrelated snippets keep their order
+    let mut best = 0;
+    let mut best_score = f32::MIN;
+    for (i, candidate) in remaining.iter().enumerate() {
+      let score = similarity::cosine_similarity_tuple(last.clone(), vectors[&candidate.to_string()].clone());
+
+      if score > best_score {
+        best = i;
+        best_score = score;
+      }
+    }
+
+    order.push(remaining.remove(best));
+  }
+
+  order
+}
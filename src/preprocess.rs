@@ -1,9 +1,32 @@
 use std::collections::HashMap;
 use rust_stemmers::{ Algorithm, Stemmer };
 use human_regex::{ one_or_more, punctuation };
+use stop_words::{ get, LANGUAGE };
+use sha2::{ Digest, Sha256 };
+use unicode_normalization::UnicodeNormalization;
 
 use crate::{Corpus, CorpusSnippets};
 
+// Stop words for an ISO 639-1 language code, falling back to English
+pub fn stop_words(language: Option<&str>) -> Vec<String> {
+  let language = match language.map(|code| code.trim().to_ascii_lowercase()).as_deref() {
+    Some("da") => LANGUAGE::Danish,
+    Some("de") => LANGUAGE::German,
+    Some("es") => LANGUAGE::Spanish,
+    Some("fi") => LANGUAGE::Finnish,
+    Some("fr") => LANGUAGE::French,
+    Some("it") => LANGUAGE::Italian,
+    Some("nl") => LANGUAGE::Dutch,
+    Some("no") => LANGUAGE::Norwegian,
+    Some("pt") => LANGUAGE::Portuguese,
+    Some("ru") => LANGUAGE::Russian,
+    Some("sv") => LANGUAGE::Swedish,
+    _ => LANGUAGE::English,
+  };
+
+  get(language)
+}
+
 pub fn corpus_tfidf_preprocess(corpus: Corpus, stop_words: Vec<String>) -> CorpusSnippets {
   let mut processed: CorpusSnippets = HashMap::new();
 
@@ -73,3 +96,168 @@ pub fn tfidf_preprocess(document: &str, stop_words: Vec<String>) -> Vec<String>
 
   clean_text
 }
+
+// Inline `#tag` tokens, the `#title` first line of a snippet is not a tag
+pub fn extract_tags(snippet: &str) -> Vec<String> {
+  let mut tags: Vec<String> = Vec::new();
+
+  for (i, line) in snippet.lines().enumerate() {
+    if i == 0 && line.starts_with('#') {
+      continue;
+    }
+
+    for word in line.split_whitespace() {
+      let Some(tag) = word.strip_prefix('#') else {
+        continue;
+      };
+
+      let tag = normalize_tag(tag.trim_end_matches(|c: char| !c.is_alphanumeric()));
+
+      if tag.chars().any(|c| c.is_alphabetic())
+        && tag.chars().all(|c| c.is_alphanumeric() || "_-/".contains(c))
+        && !tags.contains(&tag)
+      {
+        tags.push(tag);
+      }
+    }
+  }
+
+  tags
+}
+
+pub fn normalize_tag(tag: &str) -> String {
+  tag.trim()
+    .trim_start_matches('#')
+    .to_lowercase()
+}
+
+// How often each word occurs in the document, grouped by its stem
+pub fn surface_form_counts(document: &str) -> HashMap<String, HashMap<String, i64>> {
+  let en_stemmer = Stemmer::create(Algorithm::English);
+  let lowercase_text = document.to_string().to_ascii_lowercase();
+  let punctuation_regex = one_or_more(punctuation());
+  let no_punctuation_text = punctuation_regex
+    .to_regex()
+    .replace_all(&lowercase_text, "");
+
+  let mut counts: HashMap<String, HashMap<String, i64>> = HashMap::new();
+  for word in no_punctuation_text.split_whitespace() {
+    *counts.entry(en_stemmer.stem(word).to_string())
+      .or_default()
+      .entry(word.to_string())
+      .or_default() += 1;
+  }
+
+  counts
+}
+
+// The most frequent word each stem came from, ties go to the shortest word
+pub fn most_frequent_surface_forms(counts: HashMap<String, HashMap<String, i64>>) -> HashMap<String, String> {
+  counts.into_iter()
+    .filter_map(|(stem, words)| {
+      words.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.len().cmp(&a.0.len())).then(b.0.cmp(&a.0)))
+        .map(|(word, _)| (stem, word))
+    })
+    .collect()
+}
+
+pub fn surface_forms(document: &str) -> HashMap<String, String> {
+  most_frequent_surface_forms(surface_form_counts(document))
+}
+
+// A stemmed term or phrase with each stem replaced by its surface form
+pub fn unstem(phrase: &str, surface_forms: &HashMap<String, String>) -> String {
+  phrase.split_whitespace()
+    .map(|stem| surface_forms.get(stem).map(|word| word.as_str()).unwrap_or(stem))
+    .collect::<Vec<&str>>()
+    .join(" ")
+}
+
+// Puts a snippet in the form it is stored in: NFC, `\n` line endings, no
+// trailing whitespace or blank edges, `-` for every bullet list marker and
+// `1.` for every numbered one
+pub fn normalize_snippet(snippet: &str) -> String {
+  let snippet: String = snippet.replace("\r\n", "\n")
+    .replace('\r', "\n")
+    .nfc()
+    .collect();
+
+  let lines: Vec<String> = snippet.lines()
+    .map(|line| {
+      let line = line.trim_end();
+      let content = line.trim_start();
+      let indent = &line[..line.len() - content.len()];
+
+      let number = content.chars().take_while(|c| c.is_ascii_digit()).count();
+
+      if let Some(item) = content.strip_prefix("* ").or_else(|| content.strip_prefix("+ ")) {
+        format!("{}- {}", indent, item)
+      } else if let Some(item) = content[number..].strip_prefix(") ").filter(|_| number > 0) {
+        format!("{}{}. {}", indent, &content[..number], item)
+      } else {
+        line.to_string()
+      }
+    })
+    .collect();
+
+  lines.join("\n").trim_matches('\n').to_string()
+}
+
+// Hex encoded SHA-256 of the stored text, what snippets are unique by
+pub fn content_hash(snippet: &str) -> String {
+  Sha256::digest(snippet.as_bytes())
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn tfidf_preprocess_drops_stop_words_and_punctuation() {
+    assert_eq!(tfidf_preprocess("Machine learning is fun!", stop_words(None)), vec!["machin", "learn", "fun"]);
+  }
+
+  #[test]
+  fn rake_preprocess_splits_phrases_at_stop_words() {
+    assert_eq!(rake_preprocess("Machine learning is fun", stop_words(None)), vec!["machin learn", "fun"]);
+    assert!(rake_preprocess("the and of", stop_words(None)).is_empty());
+  }
+
+  #[test]
+  fn stop_words_fall_back_to_english() {
+    assert_eq!(stop_words(Some("xx")), stop_words(None));
+    assert!(stop_words(Some(" DE ")).contains(&"und".to_string()));
+  }
+
+  #[test]
+  fn extract_tags_skips_the_title_line() {
+    assert_eq!(extract_tags("#Title\nSome #Rust and #ml, not #42 or a#b"), vec!["rust", "ml"]);
+  }
+
+  #[test]
+  fn normalize_tag_lowercases_without_hash() {
+    assert_eq!(normalize_tag(" #Rust "), "rust");
+  }
+
+  #[test]
+  fn surface_forms_prefer_the_most_frequent_word() {
+    let forms = surface_forms("Learning to learn, learning fast");
+
+    assert_eq!(forms["learn"], "learning");
+    assert_eq!(unstem("learn fast", &forms), "learning fast");
+  }
+
+  #[test]
+  fn normalize_snippet_cleans_line_endings_and_lists() {
+    assert_eq!(normalize_snippet("\r\n* one  \r\n  + two\r\n3) three\n\n"), "- one\n  - two\n3. three");
+  }
+
+  #[test]
+  fn content_hash_is_sha256() {
+    assert_eq!(content_hash(""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+  }
+}
@@ -85,3 +85,36 @@ fn phrase_degree_scores(phrases: Vec<&str>, degree_scores: HashMap<String, f32>)
 
   score
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn phrases(phrases: &[&str]) -> Vec<String> {
+    phrases.iter().map(|phrase| phrase.to_string()).collect()
+  }
+
+  #[test]
+  fn rake_scores_phrases_and_words() {
+    let expected: HashMap<String, f32> = [("machin learn", 4.), ("fun", 1.), ("machin", 2.), ("learn", 2.)]
+      .into_iter()
+      .map(|(phrase, score)| (phrase.to_string(), score))
+      .collect();
+
+    assert_eq!(rake(phrases(&["machin learn", "fun"])), expected);
+  }
+
+  #[test]
+  fn rake_divides_degree_by_frequency() {
+    let scores = rake(phrases(&["a b", "a"]));
+
+    assert_eq!(scores["a"], 1.5);
+    assert_eq!(scores["b"], 2.);
+    assert_eq!(scores["a b"], 3.5);
+  }
+
+  #[test]
+  fn rake_of_nothing_is_empty() {
+    assert!(rake(Vec::new()).is_empty());
+  }
+}
@@ -31,7 +31,8 @@ pub fn cosine_similarity_tuple(scores_1: HashMap<String, f32>, scores_2: HashMap
   if magnitude_a == 0. || magnitude_b == 0. {
     0.
   } else {
-    dot_product / (magnitude_a * magnitude_b)
+    // rounding can push parallel vectors just past 1
+    (dot_product / (magnitude_a * magnitude_b)).clamp(0., 1.)
   }
 }
 
@@ -60,5 +61,61 @@ pub fn weighted_jaccard_similarity(document_1: Vec<String>, document_2: Vec<Stri
       )
     ).sum::<f32>();
 
-  minimum / maximum
+  // neither document has a scored phrase, so there is nothing to overlap
+  if maximum == 0. {
+    0.
+  } else {
+    minimum / maximum
+  }
+}
+
+// overlap of two sets of labels such as tags
+pub fn jaccard_similarity(labels_1: Vec<String>, labels_2: Vec<String>) -> f32 {
+  let labels_1: HashSet<String> = HashSet::from_iter(labels_1);
+  let labels_2: HashSet<String> = HashSet::from_iter(labels_2);
+
+  let union = labels_1.union(&labels_2).count();
+
+  if union == 0 {
+    0.
+  } else {
+    labels_1.intersection(&labels_2).count() as f32 / union as f32
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn scores(scores: &[(&str, f32)]) -> HashMap<String, f32> {
+    scores.iter().map(|(term, score)| (term.to_string(), *score)).collect()
+  }
+
+  #[test]
+  fn cosine_similarity_known_answers() {
+    let a = scores(&[("a", 1.), ("b", 1.)]);
+
+    assert_eq!(cosine_similarity_tuple(a.clone(), a.clone()), 1.);
+    assert_eq!(cosine_similarity_tuple(a.clone(), scores(&[("c", 1.)])), 0.);
+    assert!((cosine_similarity_tuple(a.clone(), scores(&[("a", 1.)])) - 0.70710677).abs() < 1e-6);
+    assert_eq!(cosine_similarity_tuple(a, HashMap::new()), 0.);
+  }
+
+  #[test]
+  fn weighted_jaccard_similarity_known_answers() {
+    let document_1 = vec!["a b".to_string(), "c".to_string()];
+    let document_2 = vec!["a b".to_string()];
+
+    assert_eq!(weighted_jaccard_similarity(document_1, document_2, scores(&[("a b", 4.), ("c", 1.)]), scores(&[("a b", 2.)])), 0.4);
+    assert_eq!(weighted_jaccard_similarity(Vec::new(), Vec::new(), HashMap::new(), HashMap::new()), 0.);
+  }
+
+  #[test]
+  fn jaccard_similarity_known_answers() {
+    let labels = |labels: &[&str]| labels.iter().map(|label| label.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(jaccard_similarity(labels(&["a", "b"]), labels(&["b", "c"])), 1. / 3.);
+    assert_eq!(jaccard_similarity(labels(&["a", "a"]), labels(&["a"])), 1.);
+    assert_eq!(jaccard_similarity(Vec::new(), Vec::new()), 0.);
+  }
 }
@@ -1,11 +1,9 @@
 use std::collections::HashMap;
 
-use anyhow::{ Ok, Result };
-use sqlx::{FromRow, SqlitePool};
+use crate::error::{ PageCompilerError, Result };
+use sqlx::{Connection, FromRow, QueryBuilder, Sqlite, SqliteConnection};
 
-use crate::{Corpus, CorpusSnippets};
-
-type Document = HashMap<String, String>;
+use crate::{front_matter::SnippetMetadata, preprocess, Corpus, CorpusSnippets};
 
 #[derive(Debug, FromRow, Clone)]
 pub struct Snippet {
@@ -15,9 +13,9 @@ pub struct Snippet {
 
 #[derive(Debug, FromRow, Clone)]
 pub struct SnippetRow {
-  snippet_id: i32,
-  snippet: String,
-  document_id: i32,
+  pub snippet_id: i32,
+  pub snippet: String,
+  pub document_id: i32,
 }
 
 #[derive(Debug, FromRow, Clone)]
@@ -32,31 +30,134 @@ pub struct Term {
   document_name: String,
 }
 
+#[derive(Debug, FromRow, Clone)]
+pub struct Tag {
+  tag: String,
+  document_name: String,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct TagCount {
+  pub tag: String,
+  pub snippets: i64,
+  pub documents: i64,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct SearchResult {
+  pub snippet_id: i32,
+  pub document_name: String,
+  pub snippet: String,
+  pub excerpt: String,
+  pub rank: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SearchFilter {
+  pub document: Option<String>,
+  pub tag: Option<String>,
+  pub from: Option<String>,
+  pub to: Option<String>,
+}
+
+#[derive(Debug, FromRow, Clone)]
+struct SummaryRow {
+  document_name: String,
+  summary: String,
+}
+
+#[derive(Debug, FromRow, Clone)]
+struct SurfaceForm {
+  stem: String,
+  surface: String,
+  document_name: String,
+  count: i64,
+}
+
 #[derive(Debug, FromRow, Clone)]
 struct DocumentRow {
   document_id: i32,
   document_name: String,
 }
 
-pub async fn init(db: &SqlitePool) -> Result<()> {
+// rows per multi-row insert, well under SQLite's limit on bound parameters
+const INSERT_BATCH_SIZE: usize = 500;
+
+// A stored snippet with what it can be ordered by within its document
+#[derive(Debug, FromRow, Clone)]
+pub struct OrderedSnippet {
+  pub snippet_id: i32,
+  pub snippet: String,
+  pub document_name: String,
+  // the date from its metadata, or when it was stored
+  pub date: Option<String>,
+  pub position: Option<i64>,
+}
+
+// A prior version of a snippet, with the document it was in at the time
+#[derive(Debug, FromRow, Clone)]
+pub struct Revision {
+  pub revision_id: i32,
+  pub snippet_id: i32,
+  pub snippet: String,
+  pub document_name: String,
+  pub reason: String,
+  pub created_at: String,
+}
+
+// A change to the database that can be undone as a whole
+#[derive(Debug, FromRow, Clone)]
+pub struct Operation {
+  pub operation_id: i64,
+  pub kind: String,
+  pub detail: String,
+  pub created_at: String,
+}
+
+// A document and the parent it is a section of, sections are ordered by
+// their position under the parent
+#[derive(Debug, FromRow, Clone)]
+pub struct DocumentNode {
+  pub document_name: String,
+  pub parent_name: Option<String>,
+  pub position: Option<i64>,
+}
+
+// Creates the schema and migrates older databases in one transaction, so a
+// migration that fails leaves the database as it was
+pub async fn init(db: &mut SqliteConnection) -> Result<()> {
+  let mut tx = db.begin().await?;
+  migrate(&mut tx).await?;
+  tx.commit().await?;
+
+  Ok(())
+}
+
+async fn migrate(db: &mut SqliteConnection) -> Result<()> {
   sqlx::query(r#"
     CREATE TABLE IF NOT EXISTS Document (
       document_id INTEGER PRIMARY KEY AUTOINCREMENT,
-      document_name TEXT UNIQUE
+      document_name TEXT UNIQUE,
+      parent_id INTEGER,
+      position INTEGER,
+      FOREIGN KEY (parent_id)
+        REFERENCES Document (document_id)
     );
-  "#).execute(db)
+  "#).execute(&mut *db)
     .await?;
 
   sqlx::query(r#"
     CREATE TABLE IF NOT EXISTS Snippet (
       snippet_id INTEGER PRIMARY KEY AUTOINCREMENT,
-      snippet TEXT NOT NULL UNIQUE,
+      snippet TEXT NOT NULL,
       document_id INTEGER NOT NULL,
-      UNIQUE (snippet, document_id),
+      created_at TEXT,
+      content_hash TEXT,
+      position INTEGER,
       FOREIGN KEY (document_id)
         REFERENCES Document (document_id)
     );
- "#).execute(db)
+ "#).execute(&mut *db)
     .await?;
 
   sqlx::query(r#"
@@ -67,7 +168,7 @@ pub async fn init(db: &SqlitePool) -> Result<()> {
       FOREIGN KEY (snippet_id)
         REFERENCES Snippet (snippet_id)
     );
-  "#).execute(db)
+  "#).execute(&mut *db)
     .await?;
 
   sqlx::query(r#"
@@ -78,18 +179,459 @@ pub async fn init(db: &SqlitePool) -> Result<()> {
       FOREIGN KEY (snippet_id)
         REFERENCES Snippet (snippet_id)
     );
-  "#).execute(db)
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Snippet_Metadata (
+      snippet_id INTEGER PRIMARY KEY,
+      title TEXT,
+      document TEXT,
+      source TEXT,
+      date TEXT,
+      language TEXT,
+      parent TEXT,
+      FOREIGN KEY (snippet_id)
+        REFERENCES Snippet (snippet_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Tag (
+      tag_id INTEGER PRIMARY KEY AUTOINCREMENT,
+      tag_name TEXT NOT NULL UNIQUE
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Snippet_Tag (
+      tag_id INTEGER NOT NULL,
+      snippet_id INTEGER NOT NULL,
+      PRIMARY KEY (tag_id, snippet_id),
+      FOREIGN KEY (tag_id)
+        REFERENCES Tag (tag_id),
+      FOREIGN KEY (snippet_id)
+        REFERENCES Snippet (snippet_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Document_Tag (
+      tag_id INTEGER NOT NULL,
+      document_id INTEGER NOT NULL,
+      PRIMARY KEY (tag_id, document_id),
+      FOREIGN KEY (tag_id)
+        REFERENCES Tag (tag_id),
+      FOREIGN KEY (document_id)
+        REFERENCES Document (document_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  // links written in a document's text, the target may not be stored yet
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Document_Link (
+      document_id INTEGER NOT NULL,
+      target TEXT NOT NULL,
+      PRIMARY KEY (document_id, target),
+      FOREIGN KEY (document_id)
+        REFERENCES Document (document_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Summary (
+      document_id INTEGER PRIMARY KEY,
+      summary TEXT NOT NULL,
+      FOREIGN KEY (document_id)
+        REFERENCES Document (document_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS SnippetRevision (
+      revision_id INTEGER PRIMARY KEY AUTOINCREMENT,
+      snippet_id INTEGER NOT NULL,
+      snippet TEXT NOT NULL,
+      document_id INTEGER NOT NULL,
+      reason TEXT NOT NULL,
+      created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+      FOREIGN KEY (snippet_id)
+        REFERENCES Snippet (snippet_id),
+      FOREIGN KEY (document_id)
+        REFERENCES Document (document_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  let surface_forms_exist = !sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='Surface_Form'")
+    .fetch_all(&mut *db)
+    .await?
+    .is_empty();
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Surface_Form (
+      stem TEXT NOT NULL,
+      surface TEXT NOT NULL,
+      document_id INTEGER NOT NULL,
+      count INTEGER NOT NULL,
+      PRIMARY KEY (stem, surface, document_id),
+      FOREIGN KEY (document_id)
+        REFERENCES Document (document_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  // databases created before documents could be sections of another
+  add_column_if_missing(db, "Document", "parent_id", "INTEGER REFERENCES Document (document_id)").await?;
+  add_column_if_missing(db, "Document", "position", "INTEGER").await?;
+  add_column_if_missing(db, "Snippet_Metadata", "parent", "TEXT").await?;
+
+  // databases created before snippets were timestamped
+  add_column_if_missing(db, "Snippet", "created_at", "TEXT").await?;
+
+  // databases created before snippets had a position, they keep the order
+  // they were stored in
+  add_column_if_missing(db, "Snippet", "position", "INTEGER").await?;
+
+  sqlx::query(r#"
+    UPDATE Snippet SET position = (
+      SELECT COUNT(*) FROM Snippet AS Earlier
+      WHERE Earlier.document_id = Snippet.document_id AND Earlier.snippet_id <= Snippet.snippet_id
+    )
+    WHERE position IS NULL;
+  "#).execute(&mut *db)
+    .await?;
+
+  // databases created before snippets were unique by their hash
+  add_column_if_missing(db, "Snippet", "content_hash", "TEXT").await?;
+
+  let unhashed = sqlx::query_as::<_, SnippetRow>("SELECT snippet_id, snippet, document_id FROM Snippet WHERE content_hash IS NULL;")
+    .fetch_all(&mut *db)
+    .await?;
+
+  // stored text is normalized like a new submission would be, so the same
+  // text submitted again is found by its hash. A snippet that only differs
+  // from another by what normalizing removes is kept verbatim
+  for snippet_row in unhashed {
+    let normalized = preprocess::normalize_snippet(&snippet_row.snippet);
+    let taken: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM Snippet WHERE content_hash = $1);")
+      .bind(preprocess::content_hash(&normalized))
+      .fetch_one(&mut *db)
+      .await?;
+
+    let snippet = if taken || normalized.is_empty() { snippet_row.snippet } else { normalized };
+
+    sqlx::query("UPDATE Snippet SET snippet = $1, content_hash = $2 WHERE snippet_id = $3;")
+      .bind(&snippet)
+      .bind(preprocess::content_hash(&snippet))
+      .bind(snippet_row.snippet_id)
+      .execute(&mut *db)
+      .await?;
+  }
+
+  sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS Snippet_Content_Hash ON Snippet (content_hash);")
+    .execute(&mut *db)
+    .await?;
+
+  // snippets stored before surface forms were recorded, this reads the
+  // corpus so it has to wait for every column to be migrated
+  if !surface_forms_exist {
+    for (document, snippets) in load_corpus_snippets(db).await? {
+      for snippet in snippets {
+        update_surface_forms(db, &snippet, &document).await?;
+      }
+    }
+  }
+
+  let fts_exists = !sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name='Snippet_FTS'")
+    .fetch_all(&mut *db)
+    .await?
+    .is_empty();
+
+  sqlx::query(r#"
+    CREATE VIRTUAL TABLE IF NOT EXISTS Snippet_FTS USING fts5(
+      snippet,
+      content='Snippet',
+      content_rowid='snippet_id'
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TRIGGER IF NOT EXISTS Snippet_FTS_Insert AFTER INSERT ON Snippet BEGIN
+      INSERT INTO Snippet_FTS (rowid, snippet) VALUES (new.snippet_id, new.snippet);
+    END;
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TRIGGER IF NOT EXISTS Snippet_FTS_Delete AFTER DELETE ON Snippet BEGIN
+      INSERT INTO Snippet_FTS (Snippet_FTS, rowid, snippet) VALUES ('delete', old.snippet_id, old.snippet);
+    END;
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TRIGGER IF NOT EXISTS Snippet_FTS_Update AFTER UPDATE OF snippet ON Snippet BEGIN
+      INSERT INTO Snippet_FTS (Snippet_FTS, rowid, snippet) VALUES ('delete', old.snippet_id, old.snippet);
+      INSERT INTO Snippet_FTS (rowid, snippet) VALUES (new.snippet_id, new.snippet);
+    END;
+  "#).execute(&mut *db)
+    .await?;
+
+  // snippets stored before the index existed
+  if !fts_exists {
+    sqlx::query("INSERT INTO Snippet_FTS (Snippet_FTS) VALUES ('rebuild');")
+      .execute(&mut *db)
+      .await?;
+  }
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Operation (
+      operation_id INTEGER PRIMARY KEY AUTOINCREMENT,
+      kind TEXT NOT NULL,
+      detail TEXT NOT NULL,
+      created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Operation_Journal (
+      journal_id INTEGER PRIMARY KEY AUTOINCREMENT,
+      operation_id INTEGER NOT NULL,
+      statement TEXT NOT NULL,
+      FOREIGN KEY (operation_id)
+        REFERENCES Operation (operation_id)
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  // the operation being recorded, changes made outside one aren't journaled
+  sqlx::query(r#"
+    CREATE TABLE IF NOT EXISTS Current_Operation (
+      id INTEGER PRIMARY KEY CHECK (id = 1),
+      operation_id INTEGER
+    );
+  "#).execute(&mut *db)
+    .await?;
+
+  sqlx::query("INSERT OR IGNORE INTO Current_Operation (id, operation_id) VALUES (1, NULL);")
+    .execute(&mut *db)
+    .await?;
+
+  // created last so the triggers see every migrated column
+  for table in JOURNALED_TABLES {
+    create_journal_triggers(db, table).await?;
+  }
+
+  Ok(())
+}
+
+// Every table an operation can change, the full text index follows Snippet
+// through its own triggers
+const JOURNALED_TABLES: [&str; 12] = [
+  "Document", "Snippet", "TFIDF_Term", "RAKE_Phrase", "Snippet_Metadata", "Tag",
+  "Snippet_Tag", "Document_Tag", "Document_Link", "Summary", "Surface_Form", "SnippetRevision",
+];
+
+// Triggers that journal the statement undoing each insert, update and
+// delete on the table while an operation is being recorded
+async fn create_journal_triggers(db: &mut SqliteConnection, table: &str) -> Result<()> {
+  let columns: Vec<(String, String, i64)> = sqlx::query_as("SELECT name, type, pk FROM pragma_table_info($1);")
+    .bind(table)
+    .fetch_all(&mut *db)
+    .await?;
+
+  // an INTEGER PRIMARY KEY is the rowid, otherwise the rowid is restored
+  // alongside the columns so older journal entries still find the row
+  let primary_keys: Vec<&(String, String, i64)> = columns.iter().filter(|(_, _, pk)| *pk > 0).collect();
+  let rowid_alias = primary_keys.len() == 1 && primary_keys[0].1.eq_ignore_ascii_case("INTEGER");
+
+  let mut names: Vec<String> = columns.iter().map(|(name, _, _)| name.clone()).collect();
+  let mut values: Vec<String> = columns.iter().map(|(name, _, _)| format!("quote(old.{})", name)).collect();
+  if !rowid_alias {
+    names.insert(0, "rowid".to_string());
+    values.insert(0, "old.rowid".to_string());
+  }
+
+  let assignments: Vec<String> = columns.iter()
+    .map(|(name, _, _)| format!("'{} = ' || quote(old.{})", name, name))
+    .collect();
+  // update triggers fire for rows an update left as they were, which would
+  // make an operation out of nothing
+  let changed: Vec<String> = columns.iter()
+    .map(|(name, _, _)| format!("old.{} IS NOT new.{}", name, name))
+    .collect();
+
+  let journal = |statement: String| format!(
+    "INSERT INTO Operation_Journal (operation_id, statement) SELECT operation_id, {} FROM Current_Operation WHERE operation_id IS NOT NULL;",
+    statement
+  );
+
+  let triggers = [
+    (
+      format!("{}_Journal_Insert", table),
+      format!("AFTER INSERT ON {}", table),
+      journal(format!("'DELETE FROM {} WHERE rowid = ' || new.rowid", table)),
+    ),
+    (
+      format!("{}_Journal_Update", table),
+      format!("AFTER UPDATE ON {} WHEN {}", table, changed.join(" OR ")),
+      journal(format!("'UPDATE {} SET ' || {} || ' WHERE rowid = ' || old.rowid", table, assignments.join(" || ', ' || "))),
+    ),
+    (
+      format!("{}_Journal_Delete", table),
+      format!("AFTER DELETE ON {}", table),
+      journal(format!("'INSERT INTO {} ({}) VALUES (' || {} || ')'", table, names.join(", "), values.join(" || ', ' || "))),
+    ),
+  ];
+
+  for (name, event, body) in triggers {
+    let sql = format!("CREATE TRIGGER {} {} BEGIN {} END", name, event, body);
+
+    // only replaced when a migration changed the table's columns
+    let existing: Option<String> = sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'trigger' AND name = $1;")
+      .bind(&name)
+      .fetch_optional(&mut *db)
+      .await?;
+
+    if existing.as_deref() != Some(sql.as_str()) {
+      sqlx::query(&format!("DROP TRIGGER IF EXISTS {};", name))
+        .execute(&mut *db)
+        .await?;
+      sqlx::query(&sql)
+        .execute(&mut *db)
+        .await?;
+    }
+  }
+
+  Ok(())
+}
+
+// Starts recording an operation, returning None when one is already being
+// recorded so nested operations are undone together with the outer one
+pub async fn begin_operation(db: &mut SqliteConnection, kind: &str, detail: &str) -> Result<Option<i64>> {
+  let current: Option<i64> = sqlx::query_scalar("SELECT operation_id FROM Current_Operation WHERE id = 1;")
+    .fetch_one(&mut *db)
+    .await?;
+
+  if current.is_some() {
+    return Ok(None);
+  }
+
+  let operation_id = sqlx::query("INSERT INTO Operation (kind, detail, created_at) VALUES ($1, $2, CURRENT_TIMESTAMP);")
+    .bind(kind)
+    .bind(detail)
+    .execute(&mut *db)
+    .await?
+    .last_insert_rowid();
+
+  sqlx::query("UPDATE Current_Operation SET operation_id = $1 WHERE id = 1;")
+    .bind(operation_id)
+    .execute(&mut *db)
+    .await?;
+
+  Ok(Some(operation_id))
+}
+
+// Stops recording the operation begin_operation started, operations that
+// changed nothing are dropped
+pub async fn end_operation(db: &mut SqliteConnection, operation: Option<i64>) -> Result<()> {
+  let Some(operation_id) = operation else {
+    return Ok(());
+  };
+
+  sqlx::query("UPDATE Current_Operation SET operation_id = NULL WHERE id = 1;")
+    .execute(&mut *db)
+    .await?;
+
+  sqlx::query("DELETE FROM Operation WHERE operation_id = $1 AND NOT EXISTS (SELECT 1 FROM Operation_Journal WHERE operation_id = $1);")
+    .bind(operation_id)
+    .execute(&mut *db)
     .await?;
 
   Ok(())
 }
 
-pub async fn load_corpus_snippets(db: &SqlitePool) -> Result<CorpusSnippets> {
+// The most recent operations, newest first
+pub async fn load_operations(db: &mut SqliteConnection, limit: u32) -> Result<Vec<Operation>> {
+  let operations = sqlx::query_as::<_, Operation>("SELECT operation_id, kind, detail, created_at FROM Operation ORDER BY operation_id DESC LIMIT $1;")
+    .bind(limit)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(operations)
+}
+
+// Reverts the last n operations, newest first, by running their journaled
+// statements backwards. Returns the operations that were undone
+pub async fn undo_operations(db: &mut SqliteConnection, n: u32) -> Result<Vec<Operation>> {
+  let mut tx = db.begin().await?;
+
+  // an operation left open by a failure must not journal the undo itself
+  sqlx::query("UPDATE Current_Operation SET operation_id = NULL WHERE id = 1;")
+    .execute(&mut *tx)
+    .await?;
+
+  let operations = load_operations(&mut tx, n).await?;
+
+  for operation in &operations {
+    let statements: Vec<String> = sqlx::query_scalar("SELECT statement FROM Operation_Journal WHERE operation_id = $1 ORDER BY journal_id DESC;")
+      .bind(operation.operation_id)
+      .fetch_all(&mut *tx)
+      .await?;
+
+    for statement in statements {
+      sqlx::query(&statement)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    sqlx::query("DELETE FROM Operation_Journal WHERE operation_id = $1;")
+      .bind(operation.operation_id)
+      .execute(&mut *tx)
+      .await?;
+
+    sqlx::query("DELETE FROM Operation WHERE operation_id = $1;")
+      .bind(operation.operation_id)
+      .execute(&mut *tx)
+      .await?;
+  }
+
+  tx.commit().await?;
+
+  Ok(operations)
+}
+
+async fn add_column_if_missing(db: &mut SqliteConnection, table: &str, column: &str, definition: &str) -> Result<()> {
+  let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info($1);")
+    .bind(table)
+    .fetch_all(&mut *db)
+    .await?;
+
+  if !columns.iter().any(|name| name == column) {
+    sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, definition))
+      .execute(&mut *db)
+      .await?;
+  }
+
+  Ok(())
+}
+
+pub async fn load_corpus_snippets(db: &mut SqliteConnection) -> Result<CorpusSnippets> {
   let snippets = sqlx::query_as::<_, Snippet>(r#"
-    SELECT Document.document_name, snippet FROM Snippet
-    LEFT JOIN Document ON Snippet.document_id == Document.document_id;
+    SELECT Document.document_name AS document, snippet FROM Snippet
+    LEFT JOIN Document ON Snippet.document_id == Document.document_id
+    ORDER BY Snippet.position, Snippet.snippet_id;
   "#)
-    .fetch_all(db)
+    .fetch_all(&mut *db)
     .await?;
 
   let mut corpus_snippets: CorpusSnippets = HashMap::new();
@@ -100,7 +642,107 @@ pub async fn load_corpus_snippets(db: &SqlitePool) -> Result<CorpusSnippets> {
   Ok(corpus_snippets)
 }
 
-pub async fn load_corpus(db: &SqlitePool) -> Result<Corpus> {
+pub async fn load_ordered_snippets(db: &mut SqliteConnection) -> Result<Vec<OrderedSnippet>> {
+  let snippets = sqlx::query_as::<_, OrderedSnippet>(r#"
+    SELECT Snippet.snippet_id, Snippet.snippet, Document.document_name,
+      COALESCE(Snippet_Metadata.date, Snippet.created_at) AS date, Snippet.position
+    FROM Snippet
+    JOIN Document ON Document.document_id = Snippet.document_id
+    LEFT JOIN Snippet_Metadata ON Snippet_Metadata.snippet_id = Snippet.snippet_id;
+  "#)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(snippets)
+}
+
+// A document's snippets in their stored order
+pub async fn load_document_snippets(db: &mut SqliteConnection, document: &str) -> Result<Vec<OrderedSnippet>> {
+  let snippets = sqlx::query_as::<_, OrderedSnippet>(r#"
+    SELECT Snippet.snippet_id, Snippet.snippet, Document.document_name,
+      COALESCE(Snippet_Metadata.date, Snippet.created_at) AS date, Snippet.position
+    FROM Snippet
+    JOIN Document ON Document.document_id = Snippet.document_id
+    LEFT JOIN Snippet_Metadata ON Snippet_Metadata.snippet_id = Snippet.snippet_id
+    WHERE Document.document_name = $1
+    ORDER BY Snippet.position, Snippet.snippet_id;
+  "#)
+    .bind(document)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(snippets)
+}
+
+// Moves a snippet to a position within its document, counting from 1, and
+// renumbers the others around it. Positions past either end are clamped and
+// the position the snippet ends up at is returned
+pub async fn set_position(db: &mut SqliteConnection, snippet_id: i32, position: i64) -> Result<i64> {
+  let Some(snippet_row) = sqlx::query_as::<_, SnippetRow>("SELECT snippet_id, snippet, document_id FROM Snippet WHERE snippet_id = $1;")
+    .bind(snippet_id)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingSnippet(snippet_id));
+  };
+
+  let mut snippet_ids: Vec<i32> = sqlx::query_scalar("SELECT snippet_id FROM Snippet WHERE document_id = $1 ORDER BY position, snippet_id;")
+    .bind(snippet_row.document_id)
+    .fetch_all(&mut *db)
+    .await?;
+
+  snippet_ids.retain(|id| *id != snippet_id);
+  let index = (position.max(1) as usize - 1).min(snippet_ids.len());
+  snippet_ids.insert(index, snippet_id);
+
+  let mut tx = db.begin().await?;
+  let operation = begin_operation(&mut tx, "move", &format!("snippet {} to position {}", snippet_id, index + 1)).await?;
+
+  for (i, id) in snippet_ids.iter().enumerate() {
+    sqlx::query("UPDATE Snippet SET position = $1 WHERE snippet_id = $2;")
+      .bind(i as i64 + 1)
+      .bind(id)
+      .execute(&mut *tx)
+      .await?;
+  }
+
+  end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(index as i64 + 1)
+}
+
+pub async fn move_up(db: &mut SqliteConnection, snippet_id: i32) -> Result<i64> {
+  let position = snippet_position(db, snippet_id).await?;
+
+  set_position(db, snippet_id, position - 1).await
+}
+
+pub async fn move_down(db: &mut SqliteConnection, snippet_id: i32) -> Result<i64> {
+  let position = snippet_position(db, snippet_id).await?;
+
+  set_position(db, snippet_id, position + 1).await
+}
+
+// Where a snippet currently sits in its document, counting from 1
+async fn snippet_position(db: &mut SqliteConnection, snippet_id: i32) -> Result<i64> {
+  let position: Option<i64> = sqlx::query_scalar(r#"
+    SELECT COUNT(*) FROM Snippet AS Earlier
+    JOIN Snippet ON Snippet.document_id = Earlier.document_id
+    WHERE Snippet.snippet_id = $1
+      AND (Earlier.position, Earlier.snippet_id) <= (Snippet.position, Snippet.snippet_id)
+    HAVING COUNT(*) > 0;
+  "#)
+    .bind(snippet_id)
+    .fetch_optional(&mut *db)
+    .await?;
+
+  match position {
+    Some(position) => Ok(position),
+    None => Err(PageCompilerError::MissingSnippet(snippet_id)),
+  }
+}
+
+pub async fn load_corpus(db: &mut SqliteConnection) -> Result<Corpus> {
   let corpus: Corpus = load_corpus_snippets(db)
     .await?
     .into_iter()
@@ -113,14 +755,149 @@ pub async fn load_corpus(db: &SqlitePool) -> Result<Corpus> {
   Ok(corpus)
 }
 
-pub async fn load_tfidf_data(db: &SqlitePool) -> Result<CorpusSnippets> {
+pub async fn load_document_names(db: &mut SqliteConnection) -> Result<Vec<String>> {
+  let documents = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document;")
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(documents.into_iter().map(|document| document.document_name).collect())
+}
+
+// Every document with its parent, sections in order and top-level
+// documents by name
+pub async fn load_hierarchy(db: &mut SqliteConnection) -> Result<Vec<DocumentNode>> {
+  let nodes = sqlx::query_as::<_, DocumentNode>(r#"
+    SELECT Document.document_name, Parent.document_name AS parent_name, Document.position FROM Document
+    LEFT JOIN Document AS Parent ON Parent.document_id = Document.parent_id
+    ORDER BY Document.position, Document.document_name;
+  "#)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(nodes)
+}
+
+// Makes a document the last section of parent, or a top-level document again
+// when there is no parent, an empty parent document is created if needed and
+// removed again once its last section leaves
+pub async fn set_parent(db: &mut SqliteConnection, document: &str, parent: Option<&str>) -> Result<()> {
+  let Some(former_parent) = sqlx::query_scalar::<_, Option<i64>>("SELECT parent_id FROM Document WHERE document_name = $1;")
+    .bind(document)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingDocument(document.to_string()));
+  };
+
+  let Some(parent) = parent else {
+    let mut tx = db.begin().await?;
+    let operation = begin_operation(&mut tx, "unnest", document).await?;
+
+    sqlx::query("UPDATE Document SET parent_id = NULL, position = NULL WHERE document_name = $1;")
+      .bind(document)
+      .execute(&mut *tx)
+      .await?;
+
+    if let Some(former_parent) = former_parent {
+      remove_empty_document(&mut tx, former_parent).await?;
+    }
+
+    end_operation(&mut tx, operation).await?;
+    tx.commit().await?;
+
+    return Ok(());
+  };
+
+  // the parent can't be the document itself or one of its sections
+  let cycle: i64 = sqlx::query_scalar(r#"
+    WITH RECURSIVE Ancestor (document_id, parent_id) AS (
+      SELECT document_id, parent_id FROM Document WHERE document_name = $1
+      UNION
+      SELECT Document.document_id, Document.parent_id FROM Document
+      JOIN Ancestor ON Document.document_id = Ancestor.parent_id
+    )
+    SELECT COUNT(*) FROM Ancestor
+    JOIN Document ON Document.document_id = Ancestor.document_id
+    WHERE Document.document_name = $2;
+  "#)
+    .bind(parent)
+    .bind(document)
+    .fetch_one(&mut *db)
+    .await?;
+
+  if cycle > 0 {
+    return Err(PageCompilerError::CyclicHierarchy(document.to_string()));
+  }
+
+  let mut tx = db.begin().await?;
+  let operation = begin_operation(&mut tx, "nest", &format!("{} under {}", document, parent)).await?;
+
+  sqlx::query("INSERT OR IGNORE INTO Document (document_name) VALUES ($1);")
+    .bind(parent)
+    .execute(&mut *tx)
+    .await?;
+
+  // a document already under the parent keeps its place
+  sqlx::query(r#"
+    UPDATE Document SET
+      parent_id = (SELECT document_id FROM Document WHERE document_name = $2),
+      position = (
+        SELECT COALESCE(MAX(Section.position), 0) + 1 FROM Document AS Section
+        JOIN Document AS Parent ON Parent.document_id = Section.parent_id
+        WHERE Parent.document_name = $2
+      )
+    WHERE document_name = $1
+      AND parent_id IS NOT (SELECT document_id FROM Document WHERE document_name = $2);
+  "#)
+    .bind(document)
+    .bind(parent)
+    .execute(&mut *tx)
+    .await?;
+
+  if let Some(former_parent) = former_parent {
+    remove_empty_document(&mut tx, former_parent).await?;
+  }
+
+  end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(())
+}
+
+// Removes a document left with neither snippets nor sections, such as a
+// parent that only existed to hold sections. One whose snippets have
+// revisions pointing at it is kept for their history
+async fn remove_empty_document(db: &mut SqliteConnection, document_id: i64) -> Result<()> {
+  let empty: bool = sqlx::query_scalar(r#"
+    SELECT NOT EXISTS (SELECT 1 FROM Snippet WHERE document_id = $1)
+      AND NOT EXISTS (SELECT 1 FROM Document WHERE parent_id = $1)
+      AND NOT EXISTS (SELECT 1 FROM SnippetRevision WHERE document_id = $1);
+  "#)
+    .bind(document_id)
+    .fetch_one(&mut *db)
+    .await?;
+
+  if !empty {
+    return Ok(());
+  }
+
+  for table in ["Document_Tag", "Document_Link", "Summary", "Surface_Form", "Document"] {
+    sqlx::query(&format!("DELETE FROM {} WHERE document_id = $1;", table))
+      .bind(document_id)
+      .execute(&mut *db)
+      .await?;
+  }
+
+  Ok(())
+}
+
+pub async fn load_tfidf_data(db: &mut SqliteConnection) -> Result<CorpusSnippets> {
   let terms = sqlx::query_as::<_, Term>(r#"
     SELECT term, Document.document_name FROM TFIDF_Term
     JOIN Snippet ON Snippet.snippet_id = TFIDF_Term.snippet_id
     JOIN Document ON Document.document_id = Snippet.document_id
-    GROUP BY term;
+    GROUP BY term, Document.document_id;
   "#)
-    .fetch_all(db)
+    .fetch_all(&mut *db)
     .await?;
 
   let mut corpus_terms: CorpusSnippets = HashMap::new();
@@ -131,14 +908,14 @@ pub async fn load_tfidf_data(db: &SqlitePool) -> Result<CorpusSnippets> {
   Ok(corpus_terms)
 }
 
-pub async fn load_rake_data(db: &SqlitePool) -> Result<CorpusSnippets> {
+pub async fn load_rake_data(db: &mut SqliteConnection) -> Result<CorpusSnippets> {
   let phrases = sqlx::query_as::<_, Phrase>(r#"
     SELECT phrase, Document.document_name FROM RAKE_Phrase
     JOIN Snippet ON Snippet.snippet_id = RAKE_Phrase.snippet_id
     JOIN Document ON Document.document_id = Snippet.document_id
-    GROUP BY phrase;
+    GROUP BY phrase, Document.document_id;
   "#)
-    .fetch_all(db)
+    .fetch_all(&mut *db)
     .await?;
 
   let mut corpus_phrases: CorpusSnippets = HashMap::new();
@@ -149,76 +926,605 @@ pub async fn load_rake_data(db: &SqlitePool) -> Result<CorpusSnippets> {
   Ok(corpus_phrases)
 }
 
-pub async fn update_tfidf_data(db: &SqlitePool, terms: Vec<String>, document: &str) -> Result<()> {
-  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT * FROM Document WHERE document_name = $1;")
+// Adds a snippet's tf-idf terms or RAKE phrases to the index with as few
+// statements as possible
+async fn insert_index_rows(db: &mut SqliteConnection, table: &str, column: &str, snippet_id: i32, values: Vec<String>) -> Result<()> {
+  for chunk in values.chunks(INSERT_BATCH_SIZE) {
+    let mut query = QueryBuilder::<Sqlite>::new(format!("INSERT OR IGNORE INTO {} ({}, snippet_id) ", table, column));
+    query.push_values(chunk, |mut row, value| {
+      row.push_bind(value).push_bind(snippet_id);
+    });
+
+    query.build()
+      .execute(&mut *db)
+      .await?;
+  }
+
+  Ok(())
+}
+
+// The id of the snippet if it was new, exact copies of a stored snippet
+// are ignored
+pub async fn add_snippet(db: &mut SqliteConnection, snippet: &str, document: &str) -> Result<Option<i32>> {
+  let document_created = sqlx::query("INSERT OR IGNORE INTO Document (document_name) VALUES ($1) ON CONFLICT(document_name) DO NOTHING;")
     .bind(document)
-    .fetch_one(db)
+    .execute(&mut *db)
+    .await?
+    .rows_affected() > 0;
+
+  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document WHERE document_name = $1;")
+    .bind(document)
+    .fetch_one(&mut *db)
     .await?;
 
-  let snippet_row = sqlx::query_as::<_, SnippetRow>("SELECT * FROM Snippet WHERE document_id = $1;")
-    .bind(document_row.document_id)
-    .fetch_one(db)
+  let document_id = document_row.document_id;
+
+  // new snippets go to the end of their document
+  let result = sqlx::query(r#"
+    INSERT OR IGNORE INTO Snippet (snippet, document_id, created_at, content_hash, position)
+    SELECT $1, $2, CURRENT_TIMESTAMP, $3, COALESCE(MAX(position), 0) + 1 FROM Snippet WHERE document_id = $2;
+  "#)
+    .bind(snippet)
+    .bind(document_id)
+    .bind(preprocess::content_hash(snippet))
+    .execute(&mut *db)
     .await?;
 
-  for term in terms {
-    sqlx::query("INSERT OR IGNORE INTO TFIDF_Term (term, snippet_id) VALUES ($1, $2) ON CONFLICT(term, snippet_id) DO NOTHING;")
-      .bind(term)
-      .bind(snippet_row.snippet_id)
-      .execute(db)
-      .await?;
+  if result.rows_affected() == 0 {
+    // a document made for a snippet that was already stored would be empty
+    if document_created {
+      sqlx::query("DELETE FROM Document WHERE document_id = $1;")
+        .bind(document_id)
+        .execute(&mut *db)
+        .await?;
+    }
+
+    return Ok(None);
   }
 
-  Ok(())
+  update_surface_forms(db, snippet, document).await?;
+
+  Ok(Some(result.last_insert_rowid() as i32))
 }
 
-pub async fn update_rake_data(db: &SqlitePool, phrases: Vec<String>, document: &str) -> Result<()> {
-  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT * FROM Document WHERE document_name = $1;")
+pub async fn add_document(db: &mut SqliteConnection, document_name: &str, snippet: &str, tfidf_terms: Vec<String>, rake_phrases: Vec<String>) -> Result<Option<i32>> {
+  let mut tx = db.begin().await?;
+  let operation = begin_operation(&mut tx, "add", document_name).await?;
+
+  let snippet_id = add_snippet(&mut tx, snippet, document_name).await?;
+  if let Some(snippet_id) = snippet_id {
+    insert_index_rows(&mut tx, "TFIDF_Term", "term", snippet_id, tfidf_terms).await?;
+    insert_index_rows(&mut tx, "RAKE_Phrase", "phrase", snippet_id, rake_phrases).await?;
+  }
+
+  end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(snippet_id)
+}
+
+// Swaps a stored snippet's text for a new version in place, replacing its
+// index rows, and returns the document it belongs to
+pub async fn replace_snippet(db: &mut SqliteConnection, snippet: &str, new_snippet: &str, tfidf_terms: Vec<String>, rake_phrases: Vec<String>) -> Result<String> {
+  let snippet_row = sqlx::query_as::<_, SnippetRow>("SELECT * FROM Snippet WHERE content_hash = $1;")
+    .bind(preprocess::content_hash(snippet))
+    .fetch_one(&mut *db)
+    .await?;
+
+  add_revision(db, snippet_row.snippet_id, "merge").await?;
+  update_snippet(db, snippet_row.snippet_id, new_snippet, tfidf_terms, rake_phrases).await
+}
+
+// Gives a snippet new text, replacing its index rows and surface forms,
+// and returns the document it belongs to
+pub async fn update_snippet(db: &mut SqliteConnection, snippet_id: i32, new_snippet: &str, tfidf_terms: Vec<String>, rake_phrases: Vec<String>) -> Result<String> {
+  let Some(snippet_row) = sqlx::query_as::<_, SnippetRow>("SELECT snippet_id, snippet, document_id FROM Snippet WHERE snippet_id = $1;")
+    .bind(snippet_id)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingSnippet(snippet_id));
+  };
+
+  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document WHERE document_id = $1;")
+    .bind(snippet_row.document_id)
+    .fetch_one(&mut *db)
+    .await?;
+
+  remove_surface_forms(db, &snippet_row.snippet, &document_row.document_name).await?;
+
+  sqlx::query("DELETE FROM TFIDF_Term WHERE snippet_id = $1;")
+    .bind(snippet_row.snippet_id)
+    .execute(&mut *db)
+    .await?;
+
+  sqlx::query("DELETE FROM RAKE_Phrase WHERE snippet_id = $1;")
+    .bind(snippet_row.snippet_id)
+    .execute(&mut *db)
+    .await?;
+
+  sqlx::query("UPDATE Snippet SET snippet = $1, content_hash = $2 WHERE snippet_id = $3;")
+    .bind(new_snippet)
+    .bind(preprocess::content_hash(new_snippet))
+    .bind(snippet_row.snippet_id)
+    .execute(&mut *db)
+    .await?;
+
+  insert_index_rows(db, "TFIDF_Term", "term", snippet_row.snippet_id, tfidf_terms).await?;
+  insert_index_rows(db, "RAKE_Phrase", "phrase", snippet_row.snippet_id, rake_phrases).await?;
+
+  update_surface_forms(db, new_snippet, &document_row.document_name).await?;
+
+  Ok(document_row.document_name)
+}
+
+// Moves a snippet to the end of another document, its index rows and tags
+// go with it
+pub async fn move_snippet(db: &mut SqliteConnection, snippet_id: i32, document: &str) -> Result<()> {
+  let Some(snippet_row) = sqlx::query_as::<_, SnippetRow>("SELECT snippet_id, snippet, document_id FROM Snippet WHERE snippet_id = $1;")
+    .bind(snippet_id)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingSnippet(snippet_id));
+  };
+
+  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document WHERE document_id = $1;")
+    .bind(snippet_row.document_id)
+    .fetch_one(&mut *db)
+    .await?;
+
+  let mut tx = db.begin().await?;
+  let operation = begin_operation(&mut tx, "move", &format!("snippet {} to {}", snippet_id, document)).await?;
+
+  add_revision(&mut tx, snippet_id, "move").await?;
+  remove_surface_forms(&mut tx, &snippet_row.snippet, &document_row.document_name).await?;
+
+  sqlx::query("INSERT OR IGNORE INTO Document (document_name) VALUES ($1);")
+    .bind(document)
+    .execute(&mut *tx)
+    .await?;
+
+  sqlx::query(r#"
+    UPDATE Snippet SET
+      document_id = (SELECT document_id FROM Document WHERE document_name = $1),
+      position = (
+        SELECT COALESCE(MAX(Other.position), 0) + 1 FROM Snippet AS Other
+        JOIN Document ON Document.document_id = Other.document_id
+        WHERE Document.document_name = $1
+      )
+    WHERE snippet_id = $2;
+  "#)
     .bind(document)
-    .fetch_one(db)
+    .bind(snippet_id)
+    .execute(&mut *tx)
     .await?;
 
-  let snippet_row = sqlx::query_as::<_, SnippetRow>("SELECT * FROM Snippet WHERE document_id = $1;")
-    .bind(document_row.document_id)
-    .fetch_one(db)
+  update_surface_forms(&mut tx, &snippet_row.snippet, document).await?;
+
+  end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(())
+}
+
+// Keeps the current text and document of a snippet before it changes,
+// reason says what is changing it: edit, move, merge or restore
+pub async fn add_revision(db: &mut SqliteConnection, snippet_id: i32, reason: &str) -> Result<()> {
+  sqlx::query(r#"
+    INSERT INTO SnippetRevision (snippet_id, snippet, document_id, reason, created_at)
+    SELECT snippet_id, snippet, document_id, $2, CURRENT_TIMESTAMP FROM Snippet WHERE snippet_id = $1;
+  "#)
+    .bind(snippet_id)
+    .bind(reason)
+    .execute(&mut *db)
     .await?;
 
-  for phrase in phrases {
-    sqlx::query("INSERT OR IGNORE INTO RAKE_Phrase (phrase, snippet_id) VALUES ($1, $2) ON CONFLICT(phrase, snippet_id) DO NOTHING;")
-      .bind(phrase)
-      .bind(snippet_row.snippet_id)
-      .execute(db)
+  Ok(())
+}
+
+// Every prior version of a snippet, oldest first
+pub async fn load_revisions(db: &mut SqliteConnection, snippet_id: i32) -> Result<Vec<Revision>> {
+  let revisions = sqlx::query_as::<_, Revision>(r#"
+    SELECT revision_id, snippet_id, snippet, Document.document_name, reason, created_at FROM SnippetRevision
+    JOIN Document ON Document.document_id = SnippetRevision.document_id
+    WHERE snippet_id = $1
+    ORDER BY revision_id;
+  "#)
+    .bind(snippet_id)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(revisions)
+}
+
+pub async fn load_revision(db: &mut SqliteConnection, revision_id: i32) -> Result<Revision> {
+  let Some(revision) = sqlx::query_as::<_, Revision>(r#"
+    SELECT revision_id, snippet_id, snippet, Document.document_name, reason, created_at FROM SnippetRevision
+    JOIN Document ON Document.document_id = SnippetRevision.document_id
+    WHERE revision_id = $1;
+  "#)
+    .bind(revision_id)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingRevision(revision_id));
+  };
+
+  Ok(revision)
+}
+
+// A snippet's current text and the document it is in
+pub async fn load_snippet(db: &mut SqliteConnection, snippet_id: i32) -> Result<OrderedSnippet> {
+  let Some(snippet) = sqlx::query_as::<_, OrderedSnippet>(r#"
+    SELECT Snippet.snippet_id, Snippet.snippet, Document.document_name,
+      COALESCE(Snippet_Metadata.date, Snippet.created_at) AS date, Snippet.position
+    FROM Snippet
+    JOIN Document ON Document.document_id = Snippet.document_id
+    LEFT JOIN Snippet_Metadata ON Snippet_Metadata.snippet_id = Snippet.snippet_id
+    WHERE Snippet.snippet_id = $1;
+  "#)
+    .bind(snippet_id)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Err(PageCompilerError::MissingSnippet(snippet_id));
+  };
+
+  Ok(snippet)
+}
+
+// The id of the stored snippet with exactly this text
+pub async fn find_snippet(db: &mut SqliteConnection, snippet: &str) -> Result<Option<i32>> {
+  let snippet_id = sqlx::query_scalar("SELECT snippet_id FROM Snippet WHERE content_hash = $1;")
+    .bind(preprocess::content_hash(snippet))
+    .fetch_optional(&mut *db)
+    .await?;
+
+  Ok(snippet_id)
+}
+
+// The tags a snippet carries, whether from its text or its front matter
+pub async fn load_snippet_tags(db: &mut SqliteConnection, snippet_id: i32) -> Result<Vec<String>> {
+  let tags = sqlx::query_scalar(r#"
+    SELECT Tag.tag_name FROM Snippet_Tag
+    JOIN Tag ON Tag.tag_id = Snippet_Tag.tag_id
+    WHERE Snippet_Tag.snippet_id = $1
+    ORDER BY Tag.tag_name;
+  "#)
+    .bind(snippet_id)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(tags)
+}
+
+// Drops the tags a snippet carries so they can be replaced
+pub async fn remove_snippet_tags(db: &mut SqliteConnection, snippet_id: i32) -> Result<()> {
+  sqlx::query("DELETE FROM Snippet_Tag WHERE snippet_id = $1;")
+    .bind(snippet_id)
+    .execute(&mut *db)
+    .await?;
+
+  Ok(())
+}
+
+pub async fn add_snippet_metadata(db: &mut SqliteConnection, snippet: &str, metadata: &SnippetMetadata) -> Result<()> {
+  sqlx::query(r#"
+    INSERT INTO Snippet_Metadata (snippet_id, title, document, source, date, language, parent)
+    SELECT snippet_id, $2, $3, $4, $5, $6, $7 FROM Snippet WHERE content_hash = $1
+    ON CONFLICT(snippet_id) DO UPDATE SET
+      title = excluded.title, document = excluded.document, source = excluded.source,
+      date = excluded.date, language = excluded.language, parent = excluded.parent;
+  "#)
+    .bind(preprocess::content_hash(snippet))
+    .bind(&metadata.title)
+    .bind(&metadata.document)
+    .bind(&metadata.source)
+    .bind(&metadata.date)
+    .bind(&metadata.language)
+    .bind(&metadata.parent)
+    .execute(&mut *db)
+    .await?;
+
+  Ok(())
+}
+
+pub async fn add_snippet_tags(db: &mut SqliteConnection, snippet: &str, tags: &[String]) -> Result<()> {
+  for tag in tags {
+    sqlx::query("INSERT OR IGNORE INTO Tag (tag_name) VALUES ($1);")
+      .bind(tag)
+      .execute(&mut *db)
+      .await?;
+
+    sqlx::query(r#"
+      INSERT OR IGNORE INTO Snippet_Tag (tag_id, snippet_id)
+      SELECT Tag.tag_id, Snippet.snippet_id FROM Tag, Snippet
+      WHERE Tag.tag_name = $1 AND Snippet.content_hash = $2;
+    "#)
+      .bind(tag)
+      .bind(preprocess::content_hash(snippet))
+      .execute(&mut *db)
       .await?;
   }
 
   Ok(())
 }
 
-pub async fn add_snippet(db: &SqlitePool, snippet: &str, document: &str) -> Result<()> {
-  sqlx::query("INSERT OR IGNORE INTO Document (document_name) VALUES ($1) ON CONFLICT(document_name) DO NOTHING;")
-    .bind(document)
-    .execute(db)
+pub async fn add_document_tags(db: &mut SqliteConnection, document: &str, tags: &[String]) -> Result<()> {
+  for tag in tags {
+    sqlx::query("INSERT OR IGNORE INTO Tag (tag_name) VALUES ($1);")
+      .bind(tag)
+      .execute(&mut *db)
+      .await?;
+
+    sqlx::query(r#"
+      INSERT OR IGNORE INTO Document_Tag (tag_id, document_id)
+      SELECT Tag.tag_id, Document.document_id FROM Tag, Document
+      WHERE Tag.tag_name = $1 AND Document.document_name = $2;
+    "#)
+      .bind(tag)
+      .bind(document)
+      .execute(&mut *db)
+      .await?;
+  }
+
+  Ok(())
+}
+
+pub async fn add_document_links(db: &mut SqliteConnection, document: &str, targets: &[String]) -> Result<()> {
+  for target in targets {
+    sqlx::query(r#"
+      INSERT OR IGNORE INTO Document_Link (document_id, target)
+      SELECT document_id, $1 FROM Document WHERE document_name = $2;
+    "#)
+      .bind(target)
+      .bind(document)
+      .execute(&mut *db)
+      .await?;
+  }
+
+  Ok(())
+}
+
+// The link targets of each document in the order they were stored
+pub async fn load_document_links(db: &mut SqliteConnection) -> Result<CorpusSnippets> {
+  let links = sqlx::query_as::<_, (String, String)>(r#"
+    SELECT Document.document_name, Document_Link.target FROM Document_Link
+    JOIN Document ON Document.document_id = Document_Link.document_id
+    ORDER BY Document_Link.rowid;
+  "#)
+    .fetch_all(&mut *db)
     .await?;
 
-  let document_row = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document WHERE document_name = $1;")
+  let mut document_links: CorpusSnippets = HashMap::new();
+  for (document, target) in links {
+    document_links.entry(document).or_default().push(target);
+  }
+
+  Ok(document_links)
+}
+
+// Tags of each document, both its own and those of its snippets
+pub async fn load_tag_data(db: &mut SqliteConnection) -> Result<CorpusSnippets> {
+  let tags = sqlx::query_as::<_, Tag>(r#"
+    SELECT Tag.tag_name AS tag, Document.document_name FROM Document_Tag
+    JOIN Tag ON Tag.tag_id = Document_Tag.tag_id
+    JOIN Document ON Document.document_id = Document_Tag.document_id
+    UNION
+    SELECT Tag.tag_name AS tag, Document.document_name FROM Snippet_Tag
+    JOIN Tag ON Tag.tag_id = Snippet_Tag.tag_id
+    JOIN Snippet ON Snippet.snippet_id = Snippet_Tag.snippet_id
+    JOIN Document ON Document.document_id = Snippet.document_id;
+  "#)
+    .fetch_all(&mut *db)
+    .await?;
+
+  let mut corpus_tags: CorpusSnippets = HashMap::new();
+  for tag in tags {
+    corpus_tags.entry(tag.document_name).or_default().push(tag.tag);
+  }
+
+  Ok(corpus_tags)
+}
+
+pub async fn load_tag_counts(db: &mut SqliteConnection) -> Result<Vec<TagCount>> {
+  let tag_counts = sqlx::query_as::<_, TagCount>(r#"
+    SELECT tag_name AS tag,
+      (SELECT COUNT(*) FROM Snippet_Tag WHERE Snippet_Tag.tag_id = Tag.tag_id) AS snippets,
+      (SELECT COUNT(*) FROM Document_Tag WHERE Document_Tag.tag_id = Tag.tag_id) AS documents
+    FROM Tag
+    ORDER BY tag_name;
+  "#)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(tag_counts)
+}
+
+// Snippets carrying the tag grouped by document, documents tagged
+// directly are included even when none of their snippets are
+pub async fn load_tagged(db: &mut SqliteConnection, tag: &str) -> Result<CorpusSnippets> {
+  let snippets = sqlx::query_as::<_, Snippet>(r#"
+    SELECT Document.document_name AS document, Snippet.snippet FROM Snippet_Tag
+    JOIN Tag ON Tag.tag_id = Snippet_Tag.tag_id
+    JOIN Snippet ON Snippet.snippet_id = Snippet_Tag.snippet_id
+    JOIN Document ON Document.document_id = Snippet.document_id
+    WHERE Tag.tag_name = $1;
+  "#)
+    .bind(tag)
+    .fetch_all(&mut *db)
+    .await?;
+
+  let documents = sqlx::query_as::<_, DocumentRow>(r#"
+    SELECT Document.document_id, Document.document_name FROM Document_Tag
+    JOIN Tag ON Tag.tag_id = Document_Tag.tag_id
+    JOIN Document ON Document.document_id = Document_Tag.document_id
+    WHERE Tag.tag_name = $1;
+  "#)
+    .bind(tag)
+    .fetch_all(&mut *db)
+    .await?;
+
+  let mut tagged: CorpusSnippets = HashMap::new();
+  for document in documents {
+    tagged.entry(document.document_name).or_default();
+  }
+  for snippet in snippets {
+    tagged.entry(snippet.document).or_default().push(snippet.snippet);
+  }
+
+  Ok(tagged)
+}
+
+// Ranked full text search, the query words must all appear in a snippet
+// and matches are highlighted with ** in the excerpt
+pub async fn search(db: &mut SqliteConnection, query: &str, filter: &SearchFilter, limit: i64) -> Result<Vec<SearchResult>> {
+  if query.trim().is_empty() {
+    return Ok(Vec::new());
+  }
+
+  let results = sqlx::query_as::<_, SearchResult>(r#"
+    SELECT Snippet.snippet_id, Document.document_name, Snippet.snippet,
+      snippet(Snippet_FTS, 0, '**', '**', '...', 16) AS excerpt,
+      bm25(Snippet_FTS) AS rank
+    FROM Snippet_FTS
+    JOIN Snippet ON Snippet.snippet_id = Snippet_FTS.rowid
+    JOIN Document ON Document.document_id = Snippet.document_id
+    LEFT JOIN Snippet_Metadata ON Snippet_Metadata.snippet_id = Snippet.snippet_id
+    WHERE Snippet_FTS MATCH $1
+      AND ($2 IS NULL OR Document.document_name = $2)
+      AND ($3 IS NULL
+        OR EXISTS (
+          SELECT 1 FROM Snippet_Tag JOIN Tag ON Tag.tag_id = Snippet_Tag.tag_id
+          WHERE Snippet_Tag.snippet_id = Snippet.snippet_id AND Tag.tag_name = $3
+        )
+        OR EXISTS (
+          SELECT 1 FROM Document_Tag JOIN Tag ON Tag.tag_id = Document_Tag.tag_id
+          WHERE Document_Tag.document_id = Document.document_id AND Tag.tag_name = $3
+        ))
+      AND ($4 IS NULL OR date(COALESCE(Snippet_Metadata.date, Snippet.created_at)) >= date($4))
+      AND ($5 IS NULL OR date(COALESCE(Snippet_Metadata.date, Snippet.created_at)) <= date($5))
+    ORDER BY rank
+    LIMIT $6;
+  "#)
+    .bind(fts_query(query))
+    .bind(&filter.document)
+    .bind(&filter.tag)
+    .bind(&filter.from)
+    .bind(&filter.to)
+    .bind(limit)
+    .fetch_all(&mut *db)
+    .await?;
+
+  Ok(results)
+}
+
+// Quotes every word so user input is never read as FTS5 query syntax
+fn fts_query(query: &str) -> String {
+  query.split_whitespace()
+    .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+pub async fn update_summary(db: &mut SqliteConnection, document: &str, summary: &str) -> Result<()> {
+  sqlx::query(r#"
+    INSERT INTO Summary (document_id, summary)
+    SELECT document_id, $2 FROM Document WHERE document_name = $1
+    ON CONFLICT(document_id) DO UPDATE SET summary = excluded.summary;
+  "#)
     .bind(document)
-    .fetch_one(db)
+    .bind(summary)
+    .execute(&mut *db)
     .await?;
 
-  let document_id = document_row.document_id;
+  Ok(())
+}
 
-  sqlx::query("INSERT OR IGNORE INTO Snippet (snippet, document_id) VALUES ($1, $2) ON CONFLICT(snippet, document_id) DO NOTHING;")
-    .bind(snippet)
-    .bind(document_id)
-    .execute(db)
+pub async fn load_summaries(db: &mut SqliteConnection) -> Result<HashMap<String, String>> {
+  let summaries = sqlx::query_as::<_, SummaryRow>(r#"
+    SELECT Document.document_name, Summary.summary FROM Summary
+    JOIN Document ON Document.document_id = Summary.document_id;
+  "#)
+    .fetch_all(&mut *db)
     .await?;
 
+  Ok(summaries.into_iter().map(|row| (row.document_name, row.summary)).collect())
+}
+
+// Counts the words of a snippet under their stems for its document
+pub async fn update_surface_forms(db: &mut SqliteConnection, snippet: &str, document: &str) -> Result<()> {
+  let Some(document_row) = sqlx::query_as::<_, DocumentRow>("SELECT document_id, document_name FROM Document WHERE document_name = $1;")
+    .bind(document)
+    .fetch_optional(&mut *db)
+    .await? else {
+    return Ok(());
+  };
+
+  let surface_forms: Vec<(String, String, i64)> = preprocess::surface_form_counts(snippet)
+    .into_iter()
+    .flat_map(|(stem, words)| words.into_iter().map(move |(surface, count)| (stem.clone(), surface, count)))
+    .collect();
+
+  for chunk in surface_forms.chunks(INSERT_BATCH_SIZE) {
+    let mut query = QueryBuilder::<Sqlite>::new("INSERT INTO Surface_Form (stem, surface, document_id, count) ");
+    query.push_values(chunk, |mut row, (stem, surface, count)| {
+      row.push_bind(stem)
+        .push_bind(surface)
+        .push_bind(document_row.document_id)
+        .push_bind(count);
+    });
+    query.push(" ON CONFLICT(stem, surface, document_id) DO UPDATE SET count = count + excluded.count");
+
+    query.build()
+      .execute(&mut *db)
+      .await?;
+  }
+
   Ok(())
 }
 
-pub async fn add_document(db: &SqlitePool, document_name: &str, snippet: &str, tfidf_terms: Vec<String>, rake_phrases: Vec<String>) -> Result<()> {
-  add_snippet(db, snippet, document_name).await?;
-  update_tfidf_data(db, tfidf_terms, document_name).await?;
-  update_rake_data(db, rake_phrases, document_name).await?;
+// Takes a snippet's words back out of its document's surface form counts
+pub async fn remove_surface_forms(db: &mut SqliteConnection, snippet: &str, document: &str) -> Result<()> {
+  for (stem, words) in preprocess::surface_form_counts(snippet) {
+    for (surface, count) in words {
+      sqlx::query(r#"
+        UPDATE Surface_Form SET count = count - $3
+        WHERE stem = $1 AND surface = $2
+          AND document_id = (SELECT document_id FROM Document WHERE document_name = $4);
+      "#)
+        .bind(&stem)
+        .bind(surface)
+        .bind(count)
+        .bind(document)
+        .execute(&mut *db)
+        .await?;
+    }
+  }
+
+  sqlx::query("DELETE FROM Surface_Form WHERE count <= 0;")
+    .execute(&mut *db)
+    .await?;
 
   Ok(())
 }
+
+// The most frequent surface form of each stem, per document
+pub async fn load_surface_forms(db: &mut SqliteConnection) -> Result<HashMap<String, HashMap<String, String>>> {
+  let surface_forms = sqlx::query_as::<_, SurfaceForm>(r#"
+    SELECT stem, surface, Document.document_name, count FROM Surface_Form
+    JOIN Document ON Document.document_id = Surface_Form.document_id;
+  "#)
+    .fetch_all(&mut *db)
+    .await?;
+
+  let mut counts: HashMap<String, HashMap<String, HashMap<String, i64>>> = HashMap::new();
+  for surface_form in surface_forms {
+    counts.entry(surface_form.document_name)
+      .or_default()
+      .entry(surface_form.stem)
+      .or_default()
+      .insert(surface_form.surface, surface_form.count);
+  }
+
+  Ok(counts.into_iter()
+    .map(|(document, counts)| (document, preprocess::most_frequent_surface_forms(counts)))
+    .collect())
+}
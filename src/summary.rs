@@ -0,0 +1,130 @@
+use crate::error::Result;
+use sqlx::{ Connection, SqliteConnection };
+
+use crate::{ preprocess, similarity, sqlite_interface, tf_idf, CorpusSnippets };
+
+pub const SUMMARY_SENTENCES: usize = 3;
+const DAMPING: f32 = 0.85;
+const ITERATIONS: usize = 50;
+const TOLERANCE: f32 = 1e-4;
+
+// Summarises a document and stores the summary alongside it
+pub async fn summarize_document(db: &mut SqliteConnection, document: &str, sentences: usize) -> Result<Option<String>> {
+  let corpus = sqlite_interface::load_corpus(db).await?;
+
+  let Some(text) = corpus.get(document) else {
+    return Ok(None);
+  };
+
+  let summary = summarize(text, sentences);
+
+  let mut tx = db.begin().await?;
+  let operation = sqlite_interface::begin_operation(&mut tx, "summarize", document).await?;
+  sqlite_interface::update_summary(&mut tx, document, &summary).await?;
+  sqlite_interface::end_operation(&mut tx, operation).await?;
+  tx.commit().await?;
+
+  Ok(Some(summary))
+}
+
+// The highest ranked sentences of the text, kept in their original order
+pub fn summarize(text: &str, sentences: usize) -> String {
+  let all_sentences = split_sentences(text);
+  let scores = text_rank(&all_sentences);
+
+  let mut ranked: Vec<usize> = (0..all_sentences.len()).collect();
+  ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+  ranked.truncate(sentences);
+  ranked.sort();
+
+  ranked.into_iter()
+    .map(|i| all_sentences[i].clone())
+    .collect::<Vec<String>>()
+    .join(" ")
+}
+
+// TextRank over a graph of sentences weighted by the cosine similarity of
+// their tf-idf vectors
+pub fn text_rank(sentences: &[String]) -> Vec<f32> {
+  let stop_words = preprocess::stop_words(None);
+
+  let corpus: CorpusSnippets = sentences.iter()
+    .enumerate()
+    .map(|(i, sentence)| (i.to_string(), preprocess::tfidf_preprocess(sentence, stop_words.clone())))
+    .collect();
+  let vectors = tf_idf::tf_idf_vectors(&corpus);
+
+  let n = sentences.len();
+  let mut weights = vec![vec![0.; n]; n];
+  for i in 0..n {
+    for j in (i + 1)..n {
+      let weight = similarity::cosine_similarity_tuple(
+        vectors[&i.to_string()].clone(),
+        vectors[&j.to_string()].clone()
+      );
+
+      weights[i][j] = weight;
+      weights[j][i] = weight;
+    }
+  }
+
+  let out_weights: Vec<f32> = weights.iter().map(|row| row.iter().sum()).collect();
+  let mut scores = vec![1.; n];
+
+  for _ in 0..ITERATIONS {
+    let mut next_scores = vec![1. - DAMPING; n];
+
+    for (i, next_score) in next_scores.iter_mut().enumerate() {
+      for j in 0..n {
+        if out_weights[j] > 0. {
+          *next_score += DAMPING * weights[j][i] / out_weights[j] * scores[j];
+        }
+      }
+    }
+
+    let change: f32 = scores.iter().zip(&next_scores).map(|(a, b)| (a - b).abs()).sum();
+    scores = next_scores;
+
+    if change < TOLERANCE {
+      break;
+    }
+  }
+
+  scores
+}
+
+// Sentences of a markdown text, headings and blank lines are dropped
+pub fn split_sentences(text: &str) -> Vec<String> {
+  let mut sentences: Vec<String> = Vec::new();
+
+  for line in text.lines() {
+    let line = line.trim();
+
+    if line.is_empty() || line.starts_with('#') {
+      continue;
+    }
+
+    let mut sentence = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+      sentence.push(c);
+
+      if ".!?".contains(c) && chars.peek().is_none_or(|next| next.is_whitespace()) {
+        push_sentence(&mut sentences, &sentence);
+        sentence.clear();
+      }
+    }
+    push_sentence(&mut sentences, &sentence);
+  }
+
+  sentences
+}
+
+fn push_sentence(sentences: &mut Vec<String>, sentence: &str) {
+  let sentence = sentence.trim();
+
+  if sentence.chars().any(|c| c.is_alphanumeric()) {
+    sentences.push(sentence.to_string());
+  }
+}
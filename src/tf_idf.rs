@@ -52,6 +52,51 @@ pub fn tf_idf_hash(document: Vec<String>, corpus: CorpusSnippets) -> HashMap<Str
   scores
 }
 
+// Sparse tf-idf vectors holding only the terms each document contains,
+// idf is computed once per term so this scales to many small documents
+pub fn tf_idf_vectors(corpus: &CorpusSnippets) -> HashMap<String, HashMap<String, f32>> {
+  weighted_vectors(corpus, idf)
+}
+
+// tf-idf vectors like tf_idf_vectors with a smoothed idf, so terms used
+// everywhere still rank below rarer ones but keep a weight, as every term
+// would in a corpus of one document
+pub fn smoothed_tf_idf_vectors(corpus: &CorpusSnippets) -> HashMap<String, HashMap<String, f32>> {
+  weighted_vectors(corpus, smoothed_idf)
+}
+
+fn weighted_vectors(corpus: &CorpusSnippets, idf: fn(&str, Vec<Vec<&str>>) -> f32) -> HashMap<String, HashMap<String, f32>> {
+  let str_corpus: Vec<Vec<&str>> = corpus
+    .values()
+    .map(|v| v.iter()
+      .map(|v| v.as_str())
+      .collect())
+    .collect();
+
+  let mut idfs: HashMap<&str, f32> = HashMap::new();
+  for document in str_corpus.iter() {
+    for term in document {
+      if !idfs.contains_key(term) {
+        idfs.insert(term, idf(term, str_corpus.clone()));
+      }
+    }
+  }
+
+  let mut vectors: HashMap<String, HashMap<String, f32>> = HashMap::new();
+  for (name, document) in corpus {
+    let str_document: Vec<&str> = document.iter().map(|v| v.as_str()).collect();
+    let vector = vectors.entry(name.to_string()).or_default();
+
+    for term in str_document.iter() {
+      if !vector.contains_key(*term) {
+        vector.insert(term.to_string(), tf(term, str_document.clone()) * idfs[term]);
+      }
+    }
+  }
+
+  vectors
+}
+
 fn tf(search_term: &str, document: Vec<&str>) -> f32 {
   let mut search_term_count = 0.;
   let mut all_term_count = 0.;
@@ -64,7 +109,12 @@ fn tf(search_term: &str, document: Vec<&str>) -> f32 {
     all_term_count += 1.;
   }
 
-  search_term_count / all_term_count
+  // an empty document contains no term at all
+  if all_term_count == 0. {
+    0.
+  } else {
+    search_term_count / all_term_count
+  }
 }
 
 fn idf(term: &str, corpus: Vec<Vec<&str>>) -> f32 {
@@ -79,5 +129,72 @@ fn idf(term: &str, corpus: Vec<Vec<&str>>) -> f32 {
     total_documents += 1.;
   };
 
-  (total_documents / count).ln()
+  // a term no document contains tells nothing apart, like one every
+  // document contains
+  if count == 0. {
+    0.
+  } else {
+    (total_documents / count).ln()
+  }
+}
+
+fn smoothed_idf(term: &str, corpus: Vec<Vec<&str>>) -> f32 {
+  let count = corpus.iter().filter(|document| document.contains(&term)).count() as f32;
+
+  ((1. + corpus.len() as f32) / (1. + count)).ln() + 1.
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn corpus(documents: &[(&str, &[&str])]) -> CorpusSnippets {
+    documents.iter()
+      .map(|(name, terms)| (name.to_string(), terms.iter().map(|term| term.to_string()).collect()))
+      .collect()
+  }
+
+  #[test]
+  fn tf_is_the_share_of_terms() {
+    assert_eq!(tf("a", vec!["a", "b", "a", "c"]), 0.5);
+    assert_eq!(tf("a", Vec::new()), 0.);
+  }
+
+  #[test]
+  fn idf_known_answers() {
+    assert_eq!(idf("a", vec![vec!["a"], vec!["b"], vec!["a", "b"], vec!["c"]]), 2_f32.ln());
+    assert_eq!(idf("a", vec![vec!["a"], vec!["a"]]), 0.);
+    assert_eq!(idf("d", vec![vec!["a"]]), 0.);
+  }
+
+  #[test]
+  fn tf_idf_vectors_hold_only_contained_terms() {
+    let vectors = tf_idf_vectors(&corpus(&[("x", &["a", "b"]), ("y", &["a"])]));
+
+    assert_eq!(vectors["x"], HashMap::from([("a".to_string(), 0.), ("b".to_string(), 0.5 * 2_f32.ln())]));
+    assert_eq!(vectors["y"], HashMap::from([("a".to_string(), 0.)]));
+  }
+
+  #[test]
+  fn smoothed_vectors_weigh_repeated_terms_more() {
+    let vectors = smoothed_tf_idf_vectors(&corpus(&[("doc", &["rust", "garbag", "rust", "collect", "rust"])]));
+
+    assert_eq!(vectors["doc"]["rust"], 0.6);
+    assert_eq!(vectors["doc"]["collect"], 0.2);
+  }
+
+  #[test]
+  fn smoothed_vectors_weigh_shared_terms_less() {
+    let vectors = smoothed_tf_idf_vectors(&corpus(&[("a", &["rust", "memori"]), ("b", &["rust", "bread"])]));
+
+    assert!(vectors["a"]["memori"] > vectors["a"]["rust"]);
+    assert!(vectors["a"]["rust"] > 0.);
+  }
+
+  #[test]
+  fn tf_idf_hash_scores_every_corpus_term() {
+    let scores = tf_idf_hash(vec!["b".to_string(), "c".to_string()], corpus(&[("x", &["a", "b"]), ("y", &["a"])]));
+
+    assert_eq!(scores, HashMap::from([("a".to_string(), 0.), ("b".to_string(), 0.5 * 2_f32.ln())]));
+  }
 }
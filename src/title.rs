@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use crate::{ preprocess, rake };
+
+pub const UNTITLED: &str = "Untitled";
+const MAX_TITLE_WORDS: usize = 5;
+
+// Titles a new document after its highest scoring RAKE phrase in surface
+// form, falling back to lower scoring phrases and then a counter when the
+// title is already taken
+pub fn generate_title(rake_data: &[String], surface_forms: &HashMap<String, String>, existing_documents: &[String]) -> String {
+  let scores = rake::rake(rake_data.to_vec());
+
+  let mut phrases: Vec<(usize, &String)> = rake_data.iter().enumerate().collect();
+  phrases.sort_by(|a, b| scores[b.1].total_cmp(&scores[a.1]).then(a.0.cmp(&b.0)));
+
+  let mut titles: Vec<String> = phrases.into_iter()
+    .map(|(_, phrase)| title_case(phrase, surface_forms))
+    .filter(|title| !title.is_empty())
+    .collect();
+
+  if titles.is_empty() {
+    titles.push(UNTITLED.to_string());
+  }
+
+  let taken = |title: &str| existing_documents.iter().any(|document| document.eq_ignore_ascii_case(title));
+
+  if let Some(title) = titles.iter().find(|title| !taken(title)) {
+    return title.clone();
+  }
+
+  let base = &titles[0];
+  let mut count = 2;
+  while taken(&format!("{} {}", base, count)) {
+    count += 1;
+  }
+
+  format!("{} {}", base, count)
+}
+
+// A stemmed phrase in surface form with its first letter capitalised,
+// stop words RAKE left at either end of the phrase are dropped
+pub fn title_case(phrase: &str, surface_forms: &HashMap<String, String>) -> String {
+  let stop_words = preprocess::stop_words(None);
+
+  let phrase = preprocess::unstem(phrase, surface_forms);
+  let mut words: Vec<&str> = phrase.split_whitespace().collect();
+
+  while words.first().is_some_and(|word| stop_words.iter().any(|stop_word| stop_word == word)) {
+    words.remove(0);
+  }
+  while words.last().is_some_and(|word| stop_words.iter().any(|stop_word| stop_word == word)) {
+    words.pop();
+  }
+
+  let title = words.into_iter()
+    .take(MAX_TITLE_WORDS)
+    .collect::<Vec<&str>>()
+    .join(" ");
+
+  let mut chars = title.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => title,
+  }
+}
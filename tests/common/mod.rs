@@ -0,0 +1,138 @@
+// shared by several test binaries, each using only some of it
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use sqlx::{Connection, SqliteConnection};
+
+use page_compiler::{ duplicate::DuplicatePolicy, submit_snippet, Submission };
+
+pub fn get_test_corpus() -> HashMap<String, String> {
+  let mut corpus = HashMap::new();
+
+  corpus.insert(
+    "doc1".to_string(),
+    "machine learning is fun".to_string(),
+  );
+
+  corpus.insert(
+    "doc2".to_string(),
+    "deep learning is powerful".to_string(),
+  );
+
+  corpus.insert(
+    "doc3".to_string(),
+    "artificial intelligence is the future".to_string(),
+  );
+
+  corpus.insert(
+    "doc4".to_string(),
+    "machine intelligence is rising".to_string(),
+  );
+
+  corpus.insert(
+    "doc5".to_string(),
+    "rust programming is fast and safe".to_string(),
+  );
+
+  corpus.insert(
+    "doc6".to_string(),
+    "python is great for data science".to_string(),
+  );
+
+  corpus.insert(
+    "doc7".to_string(),
+    "data science requires statistics".to_string(),
+  );
+
+  corpus.insert(
+    "doc8".to_string(),
+    "statistics is the backbone of ML".to_string(),
+  );
+
+  corpus.insert(
+    "doc9".to_string(),
+    "deep neural networks are revolutionary".to_string(),
+  );
+
+  corpus.insert(
+    "doc10".to_string(),
+    "rust and python are both awesome languages".to_string(),
+  );
+
+  corpus
+}
+
+pub async fn memory_db() -> SqliteConnection {
+  SqliteConnection::connect("sqlite::memory:").await.unwrap()
+}
+
+// A database holding every document of the test corpus under its own name
+pub async fn test_corpus_db() -> SqliteConnection {
+  let mut db = memory_db().await;
+
+  let mut corpus: Vec<(String, String)> = get_test_corpus().into_iter().collect();
+  corpus.sort();
+
+  for (document, text) in corpus {
+    let submission = submit_snippet(&format!("---\ndocument: {}\n---\n{}", document, text), &mut db, DuplicatePolicy::Skip).await.unwrap();
+    assert_eq!(submission, Submission::Titled(document));
+    assert_invariants(&mut db).await;
+  }
+
+  db
+}
+
+// What every operation has to leave true of the database
+pub async fn assert_invariants(db: &mut SqliteConnection) {
+  let foreign_key_violations = sqlx::query("PRAGMA foreign_key_check;")
+    .fetch_all(&mut *db)
+    .await
+    .unwrap();
+  assert!(foreign_key_violations.is_empty(), "rows reference missing rows");
+
+  // fails when the index doesn't match the stored snippets
+  sqlx::query("INSERT INTO Snippet_FTS (Snippet_FTS, rank) VALUES ('integrity-check', 1);")
+    .execute(&mut *db)
+    .await
+    .unwrap();
+
+  let shared_positions: i64 = sqlx::query_scalar(r#"
+    SELECT COUNT(*) FROM (
+      SELECT document_id FROM Snippet GROUP BY document_id, position HAVING COUNT(*) > 1
+    );
+  "#)
+    .fetch_one(&mut *db)
+    .await
+    .unwrap();
+  assert_eq!(shared_positions, 0, "snippets share a position");
+
+  let empty_documents: i64 = sqlx::query_scalar(r#"
+    SELECT COUNT(*) FROM Document
+    WHERE document_id NOT IN (SELECT document_id FROM Snippet)
+      AND document_id NOT IN (SELECT parent_id FROM Document WHERE parent_id IS NOT NULL);
+  "#)
+    .fetch_one(&mut *db)
+    .await
+    .unwrap();
+  assert_eq!(empty_documents, 0, "a document has neither snippets nor sections");
+
+  let current_operation: Option<i64> = sqlx::query_scalar("SELECT operation_id FROM Current_Operation;")
+    .fetch_one(&mut *db)
+    .await
+    .unwrap();
+  assert_eq!(current_operation, None, "an operation was left open");
+
+  let empty_operations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM Operation WHERE operation_id NOT IN (SELECT operation_id FROM Operation_Journal);")
+    .fetch_one(&mut *db)
+    .await
+    .unwrap();
+  assert_eq!(empty_operations, 0, "an operation recorded no changes");
+}
+
+pub async fn count(db: &mut SqliteConnection, table: &str) -> i64 {
+  sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {};", table))
+    .fetch_one(&mut *db)
+    .await
+    .unwrap()
+}
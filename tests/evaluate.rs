@@ -0,0 +1,35 @@
+use page_compiler::evaluate::{ evaluate, LabeledSnippet, NEW_DOCUMENT };
+
+fn labeled(label: &str, snippet: &str) -> LabeledSnippet {
+  LabeledSnippet { label: label.to_string(), snippet: snippet.to_string() }
+}
+
+#[async_std::test]
+async fn routing_decisions_are_scored_against_labels() {
+  let dataset = vec![
+    labeled("rust", "rust programming is fast and safe"),
+    labeled("ml", "machine learning is fun"),
+    labeled("rust", "rust programming is fast and safe to write"),
+    labeled("ml", "machine learning is fun and powerful"),
+    // nothing in common with the ml snippets, so it wrongly starts a document
+    labeled("ml", "gradient descent minimises a loss"),
+    labeled("rust", "rust programming is fast and safe"),
+  ];
+
+  let evaluation = evaluate(&dataset).await.unwrap();
+
+  assert_eq!(evaluation.snippets, 6);
+  assert_eq!(evaluation.correct, 5);
+  assert_eq!(evaluation.duplicates, 1);
+  assert_eq!((evaluation.new_correct, evaluation.new_predicted, evaluation.new_expected), (2, 3, 2));
+  assert_eq!(evaluation.new_recall(), 1.);
+  assert_eq!(evaluation.confusions, vec![("ml".to_string(), NEW_DOCUMENT.to_string(), 1)]);
+}
+
+#[async_std::test]
+async fn an_empty_dataset_has_no_scores() {
+  let evaluation = evaluate(&[]).await.unwrap();
+
+  assert_eq!(evaluation.accuracy(), 0.);
+  assert_eq!(evaluation.new_precision(), 0.);
+}
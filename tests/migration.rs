@@ -0,0 +1,90 @@
+mod common;
+
+use page_compiler::{ duplicate::DuplicatePolicy, error::PageCompilerError, related, sqlite_interface, submit_snippet, Submission };
+
+use common::{ assert_invariants, count, memory_db };
+
+// The schema and a snippet as the first released version stored them
+const BASELINE: &str = r#"
+  CREATE TABLE Document (
+    document_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    document_name TEXT UNIQUE
+  );
+  CREATE TABLE Snippet (
+    snippet_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    snippet TEXT NOT NULL UNIQUE,
+    document_id INTEGER NOT NULL,
+    UNIQUE (snippet, document_id),
+    FOREIGN KEY (document_id)
+      REFERENCES Document (document_id)
+  );
+  CREATE TABLE TFIDF_Term (
+    term TEXT NOT NULL,
+    snippet_id INTEGER NOT NULL,
+    PRIMARY KEY (term, snippet_id),
+    FOREIGN KEY (snippet_id)
+      REFERENCES Snippet (snippet_id)
+  );
+  CREATE TABLE RAKE_Phrase (
+    phrase TEXT NOT NULL,
+    snippet_id INTEGER NOT NULL,
+    PRIMARY KEY (phrase, snippet_id),
+    FOREIGN KEY (snippet_id)
+      REFERENCES Snippet (snippet_id)
+  );
+
+  INSERT INTO Document (document_name) VALUES ('Lua');
+  INSERT INTO Snippet (snippet, document_id) VALUES ('Lua is a great scripting language', 1);
+  INSERT INTO TFIDF_Term (term, snippet_id) VALUES ('lua', 1), ('great', 1), ('script', 1), ('languag', 1);
+  INSERT INTO RAKE_Phrase (phrase, snippet_id) VALUES ('lua', 1), ('great script languag', 1);
+
+  -- stored verbatim, before snippets were normalized
+  INSERT INTO Document (document_name) VALUES ('Notes');
+  INSERT INTO Snippet (snippet, document_id) VALUES ('Tables are the only data structure  ' || char(13, 10), 2);
+  INSERT INTO Snippet (snippet, document_id) VALUES ('Coroutines yield', 2), ('Coroutines yield ', 2);
+"#;
+
+#[async_std::test]
+async fn baseline_databases_are_migrated() {
+  let mut db = memory_db().await;
+  sqlx::raw_sql(BASELINE).execute(&mut db).await.unwrap();
+
+  // searching it unmigrated is an error rather than an empty result
+  assert!(matches!(related("lua scripting language", 5, &mut db).await, Err(PageCompilerError::Unmigrated)));
+
+  sqlite_interface::init(&mut db).await.unwrap();
+  assert_invariants(&mut db).await;
+
+  let found = related("lua scripting language", 5, &mut db).await.unwrap();
+  assert_eq!(found[0].document, "Lua");
+  assert!(found[0].terms.contains(&"scripting".to_string()));
+
+  let snippets = sqlite_interface::load_document_snippets(&mut db, "Lua").await.unwrap();
+  assert_eq!(snippets.len(), 1);
+  assert_eq!(snippets[0].position, Some(1));
+
+  // the backfill runs once every column it reads exists
+  assert_eq!(sqlite_interface::load_surface_forms(&mut db).await.unwrap()["Lua"]["script"], "scripting");
+  assert_eq!(sqlite_interface::find_snippet(&mut db, "Lua is a great scripting language").await.unwrap(), Some(1));
+
+  // legacy text is hashed as it would be submitted now, unless that would
+  // make two snippets the same
+  let notes = sqlite_interface::load_document_snippets(&mut db, "Notes").await.unwrap();
+  let notes: Vec<&str> = notes.iter().map(|note| note.snippet.as_str()).collect();
+  assert_eq!(notes, vec!["Tables are the only data structure", "Coroutines yield", "Coroutines yield "]);
+
+  let snippets = count(&mut db, "Snippet").await;
+  let submission = submit_snippet("Tables are the only data structure\r\n", &mut db, DuplicatePolicy::Keep).await.unwrap();
+  assert!(matches!(submission, Submission::Duplicate(_, _)));
+  assert_eq!(count(&mut db, "Snippet").await, snippets);
+
+  // running it again changes nothing
+  let surface_forms = count(&mut db, "Surface_Form").await;
+  sqlite_interface::init(&mut db).await.unwrap();
+  assert_eq!(count(&mut db, "Surface_Form").await, surface_forms);
+
+  let submission = submit_snippet("---\ndocument: Lua\n---\nLua is used in games", &mut db, DuplicatePolicy::Skip).await.unwrap();
+  assert_eq!(submission, Submission::Titled("Lua".to_string()));
+  assert_eq!(sqlite_interface::load_document_snippets(&mut db, "Lua").await.unwrap()[1].position, Some(2));
+  assert_invariants(&mut db).await;
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use std::{ env, fs, path::PathBuf, process };
+
+use sqlx::sqlite::SqlitePoolOptions;
+
+use page_compiler::{ import, obsidian, sqlite_interface };
+
+// A directory of its own for each test, removed before use
+fn scratch_dir(name: &str) -> PathBuf {
+  let dir = env::temp_dir().join(format!("page_compiler_{}_{}", name, process::id()));
+  let _ = fs::remove_dir_all(&dir);
+  fs::create_dir_all(&dir).unwrap();
+
+  dir
+}
+
+#[async_std::test]
+async fn vault_links_and_headings_survive_a_round_trip() {
+  let vault = scratch_dir("vault");
+  fs::write(vault.join("Sourdough.md"), "---\ntags: [baking]\n---\nA starter of flour and water.\n\n## Baking\n\nBake it in [[Oven|the oven]] until golden.\n").unwrap();
+  fs::write(vault.join("Oven.md"), "Ovens reach high temperatures for pizza.\n").unwrap();
+
+  // one connection, every connection to memory is a database of its own
+  let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+  let summary = obsidian::import_vault(&db, &vault, obsidian::ATTACHMENTS).await.unwrap();
+  assert_eq!(summary.notes, 2);
+
+  let mut conn = db.acquire().await.unwrap();
+  assert_eq!(sqlite_interface::load_document_links(&mut conn).await.unwrap()["Sourdough"], vec!["Oven"]);
+  let snippets = sqlite_interface::load_document_snippets(&mut conn, "Sourdough").await.unwrap();
+  assert_eq!(snippets[1].snippet, "## Baking\n\nBake it in the oven until golden.");
+  drop(conn);
+
+  let exported = scratch_dir("export");
+  obsidian::export_vault(&db, &exported).await.unwrap();
+
+  let note = fs::read_to_string(exported.join("Sourdough.md")).unwrap();
+  assert!(note.contains("## Baking"));
+  assert!(note.contains("## Links\n\n- [[Oven]]"));
+
+  fs::remove_dir_all(vault).unwrap();
+  fs::remove_dir_all(exported).unwrap();
+}
+
+#[async_std::test]
+async fn repeated_snippets_are_counted_as_duplicates() {
+  let dir = scratch_dir("duplicates");
+  fs::write(dir.join("a.md"), "# Bread\n\nFlour, water and salt.\n").unwrap();
+  fs::write(dir.join("b.md"), "# Bread\n\nFlour, water and salt.\n\n---\n\nBake it until golden.\n").unwrap();
+  fs::write(dir.join("c.md"), "# Copy\n\nFlour, water and salt.\n").unwrap();
+
+  let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+  let summary = import::import_directory(&db, &dir, import::ImportMode::Documents).await.unwrap();
+  assert_eq!(summary.snippets_added, 2);
+  assert_eq!(summary.snippets_duplicate, 2);
+  // a file with nothing new leaves no empty document behind
+  assert_eq!(summary.documents_created, vec!["Bread"]);
+
+  let vault = scratch_dir("duplicate_vault");
+  fs::write(vault.join("Copy.md"), "Flour, water and salt.\n").unwrap();
+  let summary = obsidian::import_vault(&db, &vault, obsidian::ATTACHMENTS).await.unwrap();
+  assert_eq!((summary.snippets_added, summary.snippets_duplicate), (0, 1));
+  assert!(summary.documents_created.is_empty());
+
+  let mut conn = db.acquire().await.unwrap();
+  common::assert_invariants(&mut conn).await;
+  drop(conn);
+
+  fs::remove_dir_all(vault).unwrap();
+
+  fs::remove_dir_all(dir).unwrap();
+}
+
+#[async_std::test]
+async fn an_empty_database_exports_no_notes() {
+  let exported = scratch_dir("empty_export");
+
+  let db = SqlitePoolOptions::new().max_connections(1).connect("sqlite::memory:").await.unwrap();
+  assert_eq!(obsidian::export_vault(&db, &exported).await.unwrap(), 0);
+
+  fs::remove_dir_all(exported).unwrap();
+}
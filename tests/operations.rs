@@ -0,0 +1,185 @@
+mod common;
+
+use sqlx::SqliteConnection;
+
+use page_compiler::{ duplicate::DuplicatePolicy, edit_snippet, error::PageCompilerError, history, sqlite_interface, submit_snippet };
+
+use common::{ assert_invariants, count, memory_db, test_corpus_db };
+
+async fn snippet_id(db: &mut SqliteConnection, snippet: &str) -> i32 {
+  sqlite_interface::find_snippet(db, snippet).await.unwrap().unwrap()
+}
+
+// Every table's rows, to compare the database before and after an undo
+async fn dump(db: &mut SqliteConnection) -> Vec<String> {
+  let mut rows: Vec<String> = Vec::new();
+
+  for table in ["Document", "Snippet", "TFIDF_Term", "RAKE_Phrase", "Tag", "Snippet_Tag", "Surface_Form", "SnippetRevision"] {
+    let columns: Vec<String> = sqlx::query_scalar("SELECT name FROM pragma_table_info($1);")
+      .bind(table)
+      .fetch_all(&mut *db)
+      .await
+      .unwrap();
+
+    rows.extend(
+      sqlx::query_scalar::<_, String>(&format!("SELECT json_array({}) FROM {};", columns.join(", "), table))
+        .fetch_all(&mut *db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|row| format!("{} {}", table, row))
+    );
+  }
+
+  rows.sort();
+  rows
+}
+
+#[async_std::test]
+async fn editing_reindexes_the_snippet() {
+  let mut db = test_corpus_db().await;
+  let id = snippet_id(&mut db, "rust programming is fast and safe").await;
+
+  let document = edit_snippet(id, "rust programming is fast, safe and #fun", &mut db, false).await.unwrap();
+  assert_invariants(&mut db).await;
+
+  assert_eq!(document, "doc5");
+  assert!(sqlite_interface::load_tfidf_data(&mut db).await.unwrap()["doc5"].contains(&"fun".to_string()));
+  assert_eq!(sqlite_interface::load_tag_data(&mut db).await.unwrap()["doc5"], vec!["fun"]);
+  assert_eq!(sqlite_interface::load_revisions(&mut db, id).await.unwrap().len(), 1);
+}
+
+#[async_std::test]
+async fn editing_keeps_front_matter_tags_until_front_matter_replaces_them() {
+  let mut db = memory_db().await;
+  submit_snippet("---\ndocument: Bread\ntags: [baking]\n---\nsourdough needs a #starter", &mut db, DuplicatePolicy::Skip).await.unwrap();
+  let id = snippet_id(&mut db, "sourdough needs a #starter").await;
+
+  // the inline tag goes with the text, the front matter tag stays
+  edit_snippet(id, "sourdough needs flour and #water", &mut db, false).await.unwrap();
+  assert_eq!(sqlite_interface::load_snippet_tags(&mut db, id).await.unwrap(), vec!["baking", "water"]);
+
+  edit_snippet(id, "---\ntags: [bread]\n---\nsourdough needs flour and #water", &mut db, false).await.unwrap();
+  assert_eq!(sqlite_interface::load_snippet_tags(&mut db, id).await.unwrap(), vec!["bread", "water"]);
+  assert_invariants(&mut db).await;
+}
+
+#[async_std::test]
+async fn editing_can_reroute_the_snippet() {
+  let mut db = test_corpus_db().await;
+  for snippet in ["rust has no garbage collector", "rust programming uses ownership"] {
+    submit_snippet(&format!("---\ndocument: doc5\n---\n{}", snippet), &mut db, DuplicatePolicy::Skip).await.unwrap();
+  }
+  let id = snippet_id(&mut db, "rust programming is fast and safe").await;
+
+  let document = edit_snippet(id, "deep neural networks are revolutionary", &mut db, true).await;
+  assert!(matches!(document, Err(PageCompilerError::DuplicateSnippet)));
+
+  let document = edit_snippet(id, "deep neural networks are revolutionary and fast", &mut db, true).await.unwrap();
+  assert_invariants(&mut db).await;
+
+  assert_eq!(document, "doc9");
+  assert_eq!(sqlite_interface::load_snippet(&mut db, id).await.unwrap().document_name, "doc9");
+  assert_eq!(sqlite_interface::load_document_snippets(&mut db, "doc5").await.unwrap().len(), 2);
+}
+
+#[async_std::test]
+async fn editing_only_reroutes_for_a_clearly_better_fit() {
+  let mut db = memory_db().await;
+  for (document, snippet) in [("bread", "knead the dough until smooth"), ("bread", "quantum computing jokes about bread"), ("bread", "bake it in a hot oven"), ("qubits", "quantum computing uses qubits")] {
+    submit_snippet(&format!("---\ndocument: {}\n---\n{}", document, snippet), &mut db, DuplicatePolicy::Keep).await.unwrap();
+  }
+  let id = snippet_id(&mut db, "bake it in a hot oven").await;
+
+  // qubits scores best, but not well enough to be routed to
+  let document = edit_snippet(id, "quantum computing", &mut db, true).await.unwrap();
+  assert_eq!(document, "bread");
+  assert_invariants(&mut db).await;
+}
+
+#[async_std::test]
+async fn restoring_a_revision_brings_back_the_text() {
+  let mut db = test_corpus_db().await;
+  let id = snippet_id(&mut db, "rust programming is fast and safe").await;
+
+  edit_snippet(id, "rust programming is fast", &mut db, false).await.unwrap();
+  let revision = sqlite_interface::load_revisions(&mut db, id).await.unwrap()[0].revision_id;
+
+  history::restore_revision(&mut db, revision).await.unwrap();
+  assert_invariants(&mut db).await;
+
+  assert_eq!(sqlite_interface::load_snippet(&mut db, id).await.unwrap().snippet, "rust programming is fast and safe");
+}
+
+#[async_std::test]
+async fn positions_and_nesting_keep_the_schema_consistent() {
+  let mut db = test_corpus_db().await;
+  submit_snippet("---\ndocument: doc5\n---\nrust has no garbage collector", &mut db, DuplicatePolicy::Skip).await.unwrap();
+  let id = snippet_id(&mut db, "rust has no garbage collector").await;
+
+  assert_eq!(sqlite_interface::move_up(&mut db, id).await.unwrap(), 1);
+  assert_invariants(&mut db).await;
+  assert_eq!(sqlite_interface::set_position(&mut db, id, 5).await.unwrap(), 2);
+  assert_invariants(&mut db).await;
+
+  sqlite_interface::set_parent(&mut db, "doc10", Some("doc5")).await.unwrap();
+  assert_invariants(&mut db).await;
+
+  // a parent made for nesting goes away with its last section
+  sqlite_interface::set_parent(&mut db, "doc9", Some("Neural")).await.unwrap();
+  assert_invariants(&mut db).await;
+  sqlite_interface::set_parent(&mut db, "doc9", None).await.unwrap();
+  assert_invariants(&mut db).await;
+  assert!(!sqlite_interface::load_document_names(&mut db).await.unwrap().contains(&"Neural".to_string()));
+
+  let cycle = sqlite_interface::set_parent(&mut db, "doc5", Some("doc10")).await;
+  assert!(matches!(cycle, Err(PageCompilerError::CyclicHierarchy(_))));
+  assert!(matches!(sqlite_interface::set_parent(&mut db, "nowhere", None).await, Err(PageCompilerError::MissingDocument(_))));
+  assert!(matches!(sqlite_interface::move_up(&mut db, 999).await, Err(PageCompilerError::MissingSnippet(999))));
+  assert_invariants(&mut db).await;
+}
+
+#[async_std::test]
+async fn undo_restores_the_previous_state() {
+  let mut db = test_corpus_db().await;
+  let mut states = vec![dump(&mut db).await];
+
+  submit_snippet("systems programming needs control over memory #systems", &mut db, DuplicatePolicy::Skip).await.unwrap();
+  states.push(dump(&mut db).await);
+
+  let id = snippet_id(&mut db, "machine learning is fun").await;
+  edit_snippet(id, "machine learning is fun and useful", &mut db, false).await.unwrap();
+  states.push(dump(&mut db).await);
+
+  sqlite_interface::set_parent(&mut db, "doc2", Some("doc1")).await.unwrap();
+  states.push(dump(&mut db).await);
+
+  submit_snippet("sourdough bread needs flour and water", &mut db, DuplicatePolicy::Skip).await.unwrap();
+
+  while let Some(state) = states.pop() {
+    assert_eq!(sqlite_interface::undo_operations(&mut db, 1).await.unwrap().len(), 1);
+    assert_invariants(&mut db).await;
+    assert_eq!(dump(&mut db).await, state);
+  }
+
+  // the corpus itself was submitted as ten operations
+  assert_eq!(count(&mut db, "Operation").await, 10);
+}
+
+#[async_std::test]
+async fn changes_that_change_nothing_are_not_undone() {
+  let mut db = test_corpus_db().await;
+  let id = snippet_id(&mut db, "rust programming is fast and safe").await;
+  edit_snippet(id, "rust programming is fast", &mut db, false).await.unwrap();
+  let operations = count(&mut db, "Operation").await;
+
+  // already first, and already this text
+  assert_eq!(sqlite_interface::move_up(&mut db, id).await.unwrap(), 1);
+  edit_snippet(id, "rust programming is fast", &mut db, false).await.unwrap();
+  assert_eq!(count(&mut db, "Operation").await, operations);
+
+  let undone = sqlite_interface::undo_operations(&mut db, 1).await.unwrap();
+  assert_eq!(undone[0].kind, "edit");
+  assert_eq!(sqlite_interface::load_snippet(&mut db, id).await.unwrap().snippet, "rust programming is fast and safe");
+  assert_invariants(&mut db).await;
+}
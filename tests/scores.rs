@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use proptest::{ collection::{ hash_map, vec }, prelude::* };
+
+use page_compiler::{ combined_similarity_scores, document_similarity_scores, preprocess, rake, similarity, tf_idf, CorpusSnippets, COSINE_WEIGHT, TAG_WEIGHT };
+
+fn in_unit_range(score: f32) -> bool {
+  score.is_finite() && (0. ..=1.).contains(&score)
+}
+
+// Short texts over a small vocabulary, so snippets share words with each
+// other and often consist of nothing but stop words or punctuation
+fn text() -> impl Strategy<Value = String> {
+  vec(prop::sample::select(vec!["the", "and", "of", "rust", "memory", "safety", "bread", "flour", "water", "!", "", "data", "science"]), 0..12)
+    .prop_map(|words| words.join(" "))
+}
+
+fn corpus() -> impl Strategy<Value = HashMap<String, String>> {
+  hash_map("[a-z]{1,4}", text(), 0..5)
+}
+
+fn tags() -> impl Strategy<Value = Vec<String>> {
+  vec(prop::sample::select(vec!["rust".to_string(), "food".to_string(), "ml".to_string()]), 0..3)
+}
+
+fn preprocess_corpus(corpus: &HashMap<String, String>) -> (CorpusSnippets, CorpusSnippets) {
+  let stop_words = preprocess::stop_words(None);
+
+  (
+    preprocess::corpus_tfidf_preprocess(corpus.clone(), stop_words.clone()),
+    preprocess::corpus_rake_preprocess(corpus.clone(), stop_words)
+  )
+}
+
+fn weights() -> impl Strategy<Value = HashMap<String, f32>> {
+  hash_map("[a-c]{1,2}", 0f32..100., 0..6)
+}
+
+proptest! {
+  #![proptest_config(ProptestConfig::with_cases(64))]
+
+  #[test]
+  fn cosine_similarity_is_in_unit_range(scores_1 in weights(), scores_2 in weights()) {
+    prop_assert!(in_unit_range(similarity::cosine_similarity_tuple(scores_1, scores_2)));
+  }
+
+  #[test]
+  fn weighted_jaccard_similarity_is_in_unit_range(text_1 in text(), text_2 in text()) {
+    let stop_words = preprocess::stop_words(None);
+    let phrases_1 = preprocess::rake_preprocess(&text_1, stop_words.clone());
+    let phrases_2 = preprocess::rake_preprocess(&text_2, stop_words);
+
+    let score = similarity::weighted_jaccard_similarity(phrases_1.clone(), phrases_2.clone(), rake::rake(phrases_1), rake::rake(phrases_2));
+    prop_assert!(in_unit_range(score));
+  }
+
+  #[test]
+  fn jaccard_similarity_is_in_unit_range(tags_1 in tags(), tags_2 in tags()) {
+    prop_assert!(in_unit_range(similarity::jaccard_similarity(tags_1, tags_2)));
+  }
+
+  #[test]
+  fn tf_idf_scores_are_finite(input in text(), corpus in corpus()) {
+    let (corpus_tfidf_data, _) = preprocess_corpus(&corpus);
+    let input_tfidf_data = preprocess::tfidf_preprocess(&input, preprocess::stop_words(None));
+
+    for score in tf_idf::tf_idf_hash(input_tfidf_data, corpus_tfidf_data.clone()).values() {
+      prop_assert!(score.is_finite() && *score >= 0.);
+    }
+    for vector in tf_idf::corpus_tf_idf_hash(corpus_tfidf_data.clone()).values().chain(tf_idf::tf_idf_vectors(&corpus_tfidf_data).values()) {
+      prop_assert!(vector.values().all(|score| score.is_finite() && *score >= 0.));
+    }
+  }
+
+  #[test]
+  fn combined_similarity_scores_are_in_unit_range(input in text(), input_tags in tags(), corpus in corpus(), corpus_tags in hash_map("[a-z]{1,4}", tags(), 0..5)) {
+    let (corpus_tfidf_data, corpus_rake_data) = preprocess_corpus(&corpus);
+    let stop_words = preprocess::stop_words(None);
+
+    let scores = combined_similarity_scores(
+      preprocess::tfidf_preprocess(&input, stop_words.clone()),
+      preprocess::rake_preprocess(&input, stop_words),
+      input_tags,
+      corpus_tfidf_data,
+      corpus_rake_data,
+      corpus_tags,
+      COSINE_WEIGHT,
+      TAG_WEIGHT
+    );
+
+    for (_, score) in scores {
+      prop_assert!(in_unit_range(score));
+    }
+  }
+
+  #[test]
+  fn document_similarity_scores_are_in_unit_range(corpus in corpus()) {
+    let (corpus_tfidf_data, corpus_rake_data) = preprocess_corpus(&corpus);
+
+    for scores in document_similarity_scores(corpus_tfidf_data, corpus_rake_data, COSINE_WEIGHT).values() {
+      prop_assert!(scores.iter().all(|(_, score)| in_unit_range(*score)));
+    }
+  }
+}
+
+#[test]
+fn empty_input_scores_zero() {
+  let corpus: CorpusSnippets = HashMap::from([("doc".to_string(), vec!["rust".to_string()])]);
+
+  let scores = combined_similarity_scores(Vec::new(), Vec::new(), Vec::new(), corpus.clone(), corpus, HashMap::new(), COSINE_WEIGHT, TAG_WEIGHT);
+  assert_eq!(scores, vec![("doc".to_string(), 0.)]);
+}
+
+#[test]
+fn stop_words_only_input_scores_zero() {
+  let stop_words = preprocess::stop_words(None);
+  let (corpus_tfidf_data, corpus_rake_data) = preprocess_corpus(&HashMap::from([("doc".to_string(), "rust memory safety".to_string())]));
+
+  let input_tfidf_data = preprocess::tfidf_preprocess("the and of", stop_words.clone());
+  let input_rake_data = preprocess::rake_preprocess("the and of", stop_words);
+  assert!(input_tfidf_data.is_empty() && input_rake_data.is_empty());
+
+  let scores = combined_similarity_scores(input_tfidf_data, input_rake_data, Vec::new(), corpus_tfidf_data, corpus_rake_data, HashMap::new(), COSINE_WEIGHT, TAG_WEIGHT);
+  assert_eq!(scores, vec![("doc".to_string(), 0.)]);
+}
+
+#[test]
+fn unseen_terms_have_no_weight() {
+  let corpus: CorpusSnippets = HashMap::from([("doc".to_string(), vec!["rust".to_string()])]);
+
+  assert_eq!(tf_idf::tf_idf("bread", vec!["bread"], corpus), 0.);
+}
+
+#[test]
+fn single_document_corpus_scores_are_finite() {
+  let (corpus_tfidf_data, corpus_rake_data) = preprocess_corpus(&HashMap::from([("doc".to_string(), "rust memory safety".to_string())]));
+  let stop_words = preprocess::stop_words(None);
+
+  let scores = combined_similarity_scores(
+    preprocess::tfidf_preprocess("rust memory", stop_words.clone()),
+    preprocess::rake_preprocess("rust memory", stop_words),
+    Vec::new(),
+    corpus_tfidf_data,
+    corpus_rake_data,
+    HashMap::new(),
+    COSINE_WEIGHT,
+    TAG_WEIGHT
+  );
+
+  assert_eq!(scores.len(), 1);
+  assert!(in_unit_range(scores[0].1));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use page_compiler::{ duplicate::DuplicatePolicy, error::PageCompilerError, sqlite_interface, submit_snippet, Submission };
+
+use common::{ assert_invariants, count, memory_db, test_corpus_db };
+
+async fn submit(db: &mut sqlx::SqliteConnection, snippet: &str) -> Submission {
+  let submission = submit_snippet(snippet, db, DuplicatePolicy::Skip).await.unwrap();
+  assert_invariants(db).await;
+
+  submission
+}
+
+#[async_std::test]
+async fn snippets_are_routed_to_the_matching_document() {
+  let mut db = test_corpus_db().await;
+
+  for (snippet, document) in [
+    ("rust programming is fast and safe to write", "doc5"),
+    ("machine learning is fun and powerful", "doc1"),
+    ("deep neural networks are revolutionary and powerful", "doc9"),
+    ("data science requires statistics and python", "doc7"),
+  ] {
+    match submit(&mut db, snippet).await {
+      Submission::Routed(routed, score) => {
+        assert_eq!(routed, document, "{}", snippet);
+        assert!(score >= page_compiler::THESHOLD);
+      },
+      submission => panic!("{} wasn't routed: {:?}", snippet, submission),
+    }
+  }
+
+  assert_eq!(count(&mut db, "Document").await, 10);
+  assert_eq!(count(&mut db, "Snippet").await, 14);
+}
+
+#[async_std::test]
+async fn unrelated_snippets_create_a_document() {
+  let mut db = test_corpus_db().await;
+
+  let Submission::Created(document) = submit(&mut db, "sourdough bread needs flour and water").await else {
+    panic!("no document was created");
+  };
+
+  assert!(!document.is_empty());
+  assert!(sqlite_interface::load_document_names(&mut db).await.unwrap().contains(&document));
+}
+
+#[async_std::test]
+async fn the_first_snippet_starts_a_document() {
+  let mut db = memory_db().await;
+
+  assert_eq!(submit(&mut db, "#Bread\nsourdough needs flour and water").await, Submission::Titled("Bread".to_string()));
+  assert!(matches!(submit(&mut db, "rust is fast").await, Submission::Created(_)));
+}
+
+#[async_std::test]
+async fn duplicates_are_skipped() {
+  let mut db = test_corpus_db().await;
+
+  assert_eq!(
+    submit(&mut db, "rust programming is fast and safe").await,
+    Submission::Duplicate("doc5".to_string(), "rust programming is fast and safe".to_string())
+  );
+  assert_eq!(count(&mut db, "Snippet").await, 10);
+}
+
+#[async_std::test]
+async fn near_duplicates_are_reported() {
+  let mut db = test_corpus_db().await;
+
+  for snippet in ["rust  programming is FAST and safe.", "Rust programming is fast, and it's safe"] {
+    assert_eq!(
+      submit(&mut db, snippet).await,
+      Submission::Duplicate("doc5".to_string(), "rust programming is fast and safe".to_string())
+    );
+  }
+  assert_eq!(count(&mut db, "Snippet").await, 10);
+}
+
+#[async_std::test]
+async fn empty_snippets_are_rejected() {
+  let mut db = memory_db().await;
+
+  for snippet in ["", "  \n\n", "---\ntags: [rust]\n---\n"] {
+    let error = submit_snippet(snippet, &mut db, DuplicatePolicy::Skip).await.unwrap_err();
+    assert!(matches!(error, PageCompilerError::EmptySnippet));
+  }
+}
+
+#[async_std::test]
+async fn stop_word_only_snippets_are_stored() {
+  let mut db = test_corpus_db().await;
+
+  assert!(matches!(submit(&mut db, "the and of").await, Submission::Created(_)));
+}